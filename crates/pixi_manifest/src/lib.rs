@@ -55,7 +55,7 @@ pub use system_requirements::{
     LibCFamilyAndVersion, LibCSystemRequirement, SystemRequirements, GLIBC_FAMILY, MUSL_FAMILY,
 };
 pub use target::{PackageTarget, TargetSelector, Targets, WorkspaceTarget};
-pub use task::{Task, TaskName};
+pub use task::{EnvMergeStrategy, Task, TaskName};
 use thiserror::Error;
 pub use warning::{Warning, WarningWithSource, WithWarnings};
 pub use workspace::{ChannelPriority, Workspace};
@@ -10,7 +10,8 @@ use toml_edit::{value, Array, DocumentMut, Item, Table, Value};
 use crate::{
     manifests::table_name::TableName, pypi::PyPiPackageName, toml::TomlDocument,
     utils::WithSourceCode, FeatureName, LibCSystemRequirement, ManifestKind, ManifestProvenance,
-    PyPiRequirement, PypiDependencyLocation, SpecType, SystemRequirements, Task, TomlError,
+    PyPiRequirement, PypiDependencyLocation, SpecType, SystemRequirements, TargetSelector, Task,
+    TaskName, TomlError,
 };
 
 /// Discriminates between a 'pixi.toml' and a 'pyproject.toml' manifest.
@@ -517,6 +518,63 @@ impl ManifestDocument {
         Ok(())
     }
 
+    /// Removes the given dependencies from the `depends-on` of a task in the
+    /// TOML manifest. The formatting of the task is preserved.
+    pub fn remove_task_dependencies(
+        &mut self,
+        name: &str,
+        dependencies: &[TaskName],
+        target: Option<&TargetSelector>,
+        feature_name: &FeatureName,
+    ) -> Result<(), TomlError> {
+        let table_name = TableName::new()
+            .with_prefix(self.table_prefix())
+            .with_feature_name(Some(feature_name));
+        let task_table = match target {
+            None => table_name.with_table(Some("tasks")).to_string(),
+            Some(selector) => format!("{}.{selector}.tasks", table_name.with_table(Some("target"))),
+        };
+
+        // Nothing to remove if the task is not defined in the document, this
+        // shouldn't insert empty tables.
+        let Some(task) = self
+            .manifest_mut()
+            .get_nested_table_mut(&task_table)
+            .ok()
+            .and_then(|tasks| tasks.get_mut(name))
+            .and_then(Item::as_table_like_mut)
+        else {
+            return Ok(());
+        };
+
+        let is_removed = |dep: &Value| {
+            dep.as_str()
+                .is_some_and(|dep| dependencies.iter().any(|name| name.as_str() == dep))
+        };
+        for key in ["depends-on", "depends_on"] {
+            match task.get_mut(key).and_then(Item::as_value_mut) {
+                Some(Value::Array(array)) => {
+                    // Keep the whitespace in front of the first element
+                    let first_prefix = array.get(0).and_then(|dep| dep.decor().prefix()).cloned();
+                    array.retain(|dep| !is_removed(dep));
+                    if let (Some(prefix), Some(first)) = (first_prefix, array.get_mut(0)) {
+                        first.decor_mut().set_prefix(prefix);
+                    }
+                }
+                // A single dependency is replaced by an empty list, so an alias
+                // remains an alias.
+                Some(value) if is_removed(value) => {
+                    let decor = value.decor().clone();
+                    *value = Value::Array(Array::new());
+                    *value.decor_mut() = decor;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
     /// Adds a task to the TOML manifest
     pub fn add_task(
         &mut self,
@@ -261,6 +261,51 @@ impl WorkspaceManifestMut<'_> {
         Ok(())
     }
 
+    /// Removes the given dependencies from the `depends-on` of a task.
+    ///
+    /// This function modifies both the workspace and the TOML document. Use
+    /// `ManifestProvenance::save` to persist the changes to disk.
+    pub fn remove_task_dependencies(
+        &mut self,
+        name: &TaskName,
+        dependencies: &[TaskName],
+        target: Option<&TargetSelector>,
+        feature_name: &FeatureName,
+    ) -> miette::Result<()> {
+        // Check if the task exists in the target
+        self.workspace
+            .feature_mut(feature_name)?
+            .targets
+            .for_opt_target(target)
+            .and_then(|target| target.tasks.get(name))
+            .ok_or_else(|| miette::miette!("task {} does not exist", name))?;
+
+        // Remove the dependencies from the Toml manifest
+        self.document.remove_task_dependencies(
+            name.as_str(),
+            dependencies,
+            target,
+            feature_name,
+        )?;
+
+        // Remove the dependencies from the internal manifest
+        let task = self
+            .workspace
+            .feature_mut(feature_name)?
+            .targets
+            .for_opt_target_mut(target)
+            .and_then(|target| target.tasks.get_mut(name));
+        match task {
+            Some(Task::Execute(execute)) => {
+                execute.depends_on.retain(|dep| !dependencies.contains(dep))
+            }
+            Some(Task::Alias(alias)) => alias.depends_on.retain(|dep| !dependencies.contains(dep)),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
     /// Adds an environment to the workspace. Overwrites the entry if it already
     /// exists.
     ///
@@ -2482,6 +2527,76 @@ test = "test initial"
         assert_snapshot!(manifest.document.to_string());
     }
 
+    #[test]
+    fn test_remove_task_dependencies() {
+        let file_contents = r#"
+[project]
+name = "foo"
+channels = []
+platforms = ["linux-64", "win-64"]
+
+[tasks]
+build = "make"
+# Runs the tests
+test = { cmd = "pytest", depends-on = ["build", "lint"] } # after the build
+all = { depends-on = "build" }
+
+[target.unix.tasks]
+check = { cmd = "make check", depends-on = ["build"] }
+"#;
+
+        let mut manifest = parse_pixi_toml(file_contents);
+        let mut manifest = manifest.editable();
+        for (name, target) in [
+            ("test", None),
+            ("all", None),
+            ("check", Some(TargetSelector::Unix)),
+        ] {
+            manifest
+                .remove_task_dependencies(
+                    &name.into(),
+                    &["build".into()],
+                    target.as_ref(),
+                    &FeatureName::Default,
+                )
+                .unwrap();
+        }
+
+        // A task that isn't in the document doesn't add an empty table
+        manifest
+            .document
+            .remove_task_dependencies(
+                "test",
+                &["lint".into()],
+                Some(&TargetSelector::Win),
+                &FeatureName::Default,
+            )
+            .unwrap();
+
+        // Only the removed dependencies are gone, the formatting is kept
+        assert_eq!(
+            manifest.document.to_string(),
+            file_contents
+                .replace(r#"["build", "lint"]"#, r#"["lint"]"#)
+                .replace(r#"{ depends-on = "build" }"#, "{ depends-on = [] }")
+                .replace(
+                    r#"{ cmd = "make check", depends-on = ["build"] }"#,
+                    r#"{ cmd = "make check", depends-on = [] }"#
+                )
+        );
+        let tasks = manifest
+            .workspace
+            .tasks(None, &FeatureName::Default)
+            .unwrap();
+        assert_eq!(tasks[&TaskName::from("test")].depends_on(), ["lint".into()]);
+        assert!(tasks[&TaskName::from("all")].depends_on().is_empty());
+        let tasks = manifest
+            .workspace
+            .tasks(Some(Platform::Linux64), &FeatureName::Default)
+            .unwrap();
+        assert!(tasks[&TaskName::from("check")].depends_on().is_empty());
+    }
+
     #[test]
     fn test_add_dependency() {
         let file_contents = r#"
@@ -170,6 +170,32 @@ impl Task {
         }
     }
 
+    /// True if a temporary directory should be created for this task, its path
+    /// is passed to the task in `PIXI_TASK_TEMPDIR`.
+    pub fn tempdir(&self) -> bool {
+        match self {
+            Task::Execute(execute) => execute.tempdir,
+            _ => false,
+        }
+    }
+
+    /// Returns the condition that has to hold for the task to run, if any.
+    pub fn condition(&self) -> Option<&str> {
+        match self {
+            Task::Execute(execute) => execute.condition.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns how the environment variables of the task are merged with the
+    /// variables of the activation.
+    pub fn env_merge_strategy(&self) -> EnvMergeStrategy {
+        match self {
+            Task::Execute(execute) => execute.env_merge_strategy,
+            _ => EnvMergeStrategy::default(),
+        }
+    }
+
     /// Returns the inputs of the task.
     pub fn inputs(&self) -> Option<&[String]> {
         match self {
@@ -218,6 +244,44 @@ pub struct Execute {
 
     /// Isolate the task from the running machine
     pub clean_env: bool,
+
+    /// How the environment variables of the task are merged with the
+    /// variables set by the activation of the environment
+    pub env_merge_strategy: EnvMergeStrategy,
+
+    /// Create a temporary directory for the command that is removed after it
+    /// finished
+    pub tempdir: bool,
+
+    /// A template like `{{ env.CI == 'true' }}` that is rendered with the
+    /// environment variables, the task is skipped unless it renders to `true`
+    pub condition: Option<String>,
+}
+
+/// Determines which value is used when a variable in the `env` of a task is
+/// also set by the activation of the environment.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Eq,
+    PartialEq,
+    strum::EnumString,
+    strum::Display,
+    strum::VariantNames,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum EnvMergeStrategy {
+    /// The value of the task overrides the value of the activation.
+    #[default]
+    TaskWins,
+
+    /// The value of the activation is kept.
+    ActivationWins,
+
+    /// Running the task fails if a variable is set by both.
+    Error,
 }
 
 impl From<Execute> for Task {
@@ -384,6 +448,18 @@ impl From<Task> for Item {
                 if let Some(description) = process.description {
                     table.insert("description", description.into());
                 }
+                if process.env_merge_strategy != EnvMergeStrategy::default() {
+                    table.insert(
+                        "env-merge-strategy",
+                        process.env_merge_strategy.to_string().into(),
+                    );
+                }
+                if process.tempdir {
+                    table.insert("tempdir", true.into());
+                }
+                if let Some(condition) = process.condition {
+                    table.insert("condition", condition.into());
+                }
                 Item::Value(Value::InlineTable(table))
             }
             Task::Alias(alias) => {
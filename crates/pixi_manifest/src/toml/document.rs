@@ -61,6 +61,28 @@ impl TomlDocument {
         Ok(current_table)
     }
 
+    /// Retrieve a mutable reference to a target table `table_name`
+    /// in dotted form (e.g. `table1.table2`) from the root of the document.
+    /// Unlike [`Self::get_or_insert_nested_table`] missing tables are not
+    /// inserted.
+    pub fn get_nested_table_mut<'a>(
+        &'a mut self,
+        table_name: &str,
+    ) -> Result<&'a mut dyn TableLike, TomlError> {
+        let parts: Vec<&str> = table_name.split('.').collect();
+
+        let mut current_table = self.0.as_table_mut() as &mut dyn TableLike;
+
+        for part in parts {
+            current_table = current_table
+                .get_mut(part)
+                .ok_or_else(|| TomlError::table_error(part, table_name))?
+                .as_table_like_mut()
+                .ok_or_else(|| TomlError::table_error(part, table_name))?;
+        }
+        Ok(current_table)
+    }
+
     /// Retrieve a mutable reference to a target table `table_name`
     /// in dotted form (e.g. `table1.table2`) from the root of the document.
     /// If the table is not found, it is inserted into the document.
@@ -33,6 +33,7 @@ pub use preview::TomlPreview;
 pub use pyproject::PyProjectToml;
 use rattler_conda_types::Platform;
 pub use target::TomlTarget;
+pub use task::TomlTasksDocument;
 use toml_span::{DeserError, Span};
 pub use workspace::TomlWorkspace;
 
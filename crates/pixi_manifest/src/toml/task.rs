@@ -1,4 +1,5 @@
-use pixi_toml::{OneOrMany, TomlFromStr, TomlIndexMap, TomlWith};
+use indexmap::IndexMap;
+use pixi_toml::{OneOrMany, TomlEnum, TomlFromStr, TomlIndexMap, TomlWith};
 use toml_span::{
     de_helpers::{expected, TableHelper},
     value::ValueInner,
@@ -6,14 +7,45 @@ use toml_span::{
 };
 
 use crate::{
-    task::{Alias, CmdArgs, Execute},
+    task::{Alias, CmdArgs, EnvMergeStrategy, Execute},
     warning::Deprecation,
-    Task, TaskName, WithWarnings,
+    Task, TaskName, Warning, WithWarnings,
 };
 
 /// A task defined in the manifest.
 pub type TomlTask = WithWarnings<Task>;
 
+/// A document that only contains a `[tasks]` table, like the files passed to
+/// `pixi run --task-graph-file`.
+#[derive(Debug, Default)]
+pub struct TomlTasksDocument {
+    /// The tasks in the order in which they are defined.
+    pub tasks: IndexMap<TaskName, Task>,
+
+    /// Warnings encountered while parsing the tasks.
+    pub warnings: Vec<Warning>,
+}
+
+impl<'de> toml_span::Deserialize<'de> for TomlTasksDocument {
+    fn deserialize(value: &mut Value<'de>) -> Result<Self, DeserError> {
+        let mut th = TableHelper::new(value)?;
+        let mut warnings = Vec::new();
+        let tasks = th
+            .optional::<TomlIndexMap<TaskName, TomlTask>>("tasks")
+            .map(TomlIndexMap::into_inner)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, task)| {
+                warnings.extend(task.warnings);
+                (name, task.value)
+            })
+            .collect();
+        th.finalize(None)?;
+
+        Ok(Self { tasks, warnings })
+    }
+}
+
 impl<'de> toml_span::Deserialize<'de> for TomlTask {
     fn deserialize(value: &mut toml_span::Value<'de>) -> Result<Self, DeserError> {
         let mut th = match value.take() {
@@ -58,6 +90,12 @@ impl<'de> toml_span::Deserialize<'de> for TomlTask {
                 .map(TomlIndexMap::into_inner);
             let description = th.optional("description");
             let clean_env = th.optional("clean-env").unwrap_or(false);
+            let env_merge_strategy = th
+                .optional::<TomlEnum<_>>("env-merge-strategy")
+                .map(TomlEnum::into_inner)
+                .unwrap_or_default();
+            let tempdir = th.optional("tempdir").unwrap_or(false);
+            let condition = th.optional("condition");
 
             th.finalize(None)?;
 
@@ -70,6 +108,9 @@ impl<'de> toml_span::Deserialize<'de> for TomlTask {
                 env,
                 description,
                 clean_env,
+                env_merge_strategy,
+                tempdir,
+                condition,
             })
         } else {
             let depends_on = depends_on(&mut th).unwrap_or_default();
@@ -124,4 +165,70 @@ mod test {
         assert_eq!(parsed.warnings.len(), 1);
         insta::assert_snapshot!(format_parse_error(input, parsed.warnings.remove(0)));
     }
+
+    #[test]
+    fn test_tasks_document() {
+        let input = r#"
+        [tasks]
+        lint = "ruff check"
+        test = { cmd = "pytest", depends-on = ["lint", "build"] }
+        "#;
+        let document = TomlTasksDocument::from_toml_str(input).unwrap();
+        assert_eq!(
+            document
+                .tasks
+                .keys()
+                .map(TaskName::as_str)
+                .collect::<Vec<_>>(),
+            vec!["lint", "test"]
+        );
+        assert!(document.warnings.is_empty());
+
+        // Only tasks are allowed
+        assert!(TomlTasksDocument::from_toml_str("[dependencies]\npython = \"*\"").is_err());
+    }
+
+    #[test]
+    fn test_env_merge_strategy() {
+        let parsed = TomlTask::from_toml_str(r#"cmd = "test""#).unwrap();
+        assert_eq!(
+            parsed.value.env_merge_strategy(),
+            EnvMergeStrategy::TaskWins
+        );
+
+        let parsed = TomlTask::from_toml_str(
+            r#"
+        cmd = "test"
+        env-merge-strategy = "activation-wins"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.value.env_merge_strategy(),
+            EnvMergeStrategy::ActivationWins
+        );
+
+        assert!(TomlTask::from_toml_str(
+            r#"
+        cmd = "test"
+        env-merge-strategy = "shell-wins"
+        "#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_condition() {
+        let parsed = TomlTask::from_toml_str(r#"cmd = "test""#).unwrap();
+        assert_eq!(parsed.value.condition(), None);
+
+        let parsed = TomlTask::from_toml_str(
+            r#"
+        cmd = "test"
+        condition = "{{ env.CI == 'true' }}"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(parsed.value.condition(), Some("{{ env.CI == 'true' }}"));
+    }
 }
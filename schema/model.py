@@ -310,6 +310,14 @@ Dependencies = dict[CondaPackageName, MatchSpec] | None
 TaskName = Annotated[str, Field(pattern=r"^[^\s\$]+$", description="A valid task name.")]
 
 
+class EnvMergeStrategy(str, Enum):
+    """Which value is used when a variable in `env` is also set by the activation of the environment."""
+
+    task_wins = "task-wins"
+    activation_wins = "activation-wins"
+    error = "error"
+
+
 class TaskInlineTable(StrictBaseModel):
     """A precise definition of a task."""
 
@@ -341,6 +349,10 @@ class TaskInlineTable(StrictBaseModel):
         description="A map of environment variables to values, used in the task, these will be overwritten by the shell.",
         examples=[{"key": "value"}, {"ARGUMENT": "value"}],
     )
+    env_merge_strategy: EnvMergeStrategy | None = Field(
+        None,
+        description="Which value is used when a variable in `env` is also set by the activation of the environment. Defaults to `task-wins`.",
+    )
     description: NonEmptyStr | None = Field(
         None,
         description="A short description of the task",
@@ -350,6 +362,15 @@ class TaskInlineTable(StrictBaseModel):
         None,
         description="Whether to run in a clean environment, removing all environment variables except those defined in `env` and by pixi itself.",
     )
+    tempdir: bool | None = Field(
+        None,
+        description="Whether to create a temporary directory for the task, its path is passed in `PIXI_TASK_TEMPDIR` and it is removed after the task finished.",
+    )
+    condition: NonEmptyStr | None = Field(
+        None,
+        description="A template that is rendered with the environment variables of the shell as `env`, the task is skipped unless it renders to `true`.",
+        examples=["{{ env.CI == 'true' }}"],
+    )
 
 
 #######################
@@ -17,7 +17,7 @@ use rattler_shell::{
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // Setting a base prefix for the pixi package
 const PROJECT_PREFIX: &str = "PIXI_PROJECT_";
@@ -384,8 +384,12 @@ pub(crate) fn get_static_environment_variables<'p>(
 /// Get the environment variables that are set in the current shell
 /// and strip them down to the minimal set required to run a command.
 pub(crate) fn get_clean_environment_variables() -> HashMap<String, String> {
-    let env = std::env::vars().collect::<HashMap<_, _>>();
+    clean_environment_variables(std::env::vars().collect())
+}
 
+/// Strips the given environment variables down to the minimal set required to
+/// run a command on the current platform.
+fn clean_environment_variables(env: HashMap<String, String>) -> HashMap<String, String> {
     let unix_keys = if cfg!(unix) {
         vec![
             "DISPLAY",
@@ -410,10 +414,43 @@ pub(crate) fn get_clean_environment_variables() -> HashMap<String, String> {
         vec![]
     };
 
+    // Windows tools break without these, e.g. `SYSTEMROOT` is required to
+    // initialize the network stack and `PATHEXT` to resolve executables.
+    let windows_keys = if cfg!(windows) {
+        vec![
+            "SYSTEMROOT",
+            "SYSTEMDRIVE",
+            "WINDIR",
+            "COMSPEC",
+            "PATHEXT",
+            "TEMP",
+            "TMP",
+            "OS",
+            "NUMBER_OF_PROCESSORS",
+            "PROCESSOR_ARCHITECTURE",
+            "COMPUTERNAME",
+            "USERNAME",
+            "USERDOMAIN",
+            "USERPROFILE",
+            "HOMEDRIVE",
+            "HOMEPATH",
+            "APPDATA",
+            "LOCALAPPDATA",
+            "PROGRAMDATA",
+            "PROGRAMFILES",
+            "PROGRAMFILES(X86)",
+            "PROGRAMW6432",
+            "COMMONPROGRAMFILES",
+            "COMMONPROGRAMFILES(X86)",
+        ]
+    } else {
+        vec![]
+    };
+
     let keys = unix_keys
         .into_iter()
         .chain(macos_keys)
-        // .chain(windows_keys)
+        .chain(windows_keys)
         .map(|s| s.to_string().to_uppercase())
         .collect_vec();
 
@@ -422,6 +459,92 @@ pub(crate) fn get_clean_environment_variables() -> HashMap<String, String> {
         .collect::<HashMap<String, String>>()
 }
 
+/// Returns the directories of a prefix that contain executables, in the order in
+/// which they should appear on the `PATH`.
+pub(crate) fn prefix_path_entries(prefix: &Path) -> Vec<PathBuf> {
+    if cfg!(windows) {
+        vec![
+            prefix.to_path_buf(),
+            prefix.join("Library").join("mingw-w64").join("bin"),
+            prefix.join("Library").join("usr").join("bin"),
+            prefix.join("Library").join("bin"),
+            prefix.join("Scripts"),
+            prefix.join("bin"),
+        ]
+    } else {
+        vec![prefix.join("bin"), prefix.join("sbin")]
+    }
+}
+
+/// Returns the system directories that contain the basic executables of the
+/// platform.
+fn system_path_entries() -> Vec<PathBuf> {
+    if cfg!(windows) {
+        std::env::var("SYSTEMROOT")
+            .map(|root| vec![PathBuf::from(root).join("System32")])
+            .unwrap_or_default()
+    } else {
+        vec![PathBuf::from("/usr/bin"), PathBuf::from("/bin")]
+    }
+}
+
+/// Get the environment variables that are required to run a command in the prefix of the
+/// environment without running any activation scripts. The `PATH` only contains the executable
+/// directories of the prefix and the system directories.
+pub(crate) fn get_prefix_only_environment_variables(
+    environment: &Environment<'_>,
+) -> HashMap<String, String> {
+    let prefix = environment.dir();
+    let path = std::env::join_paths(
+        prefix_path_entries(&prefix)
+            .into_iter()
+            .chain(system_path_entries()),
+    )
+    .map(|path| path.to_string_lossy().into_owned())
+    .unwrap_or_default();
+
+    get_clean_environment_variables()
+        .into_iter()
+        .chain(get_static_environment_variables(environment))
+        .chain([
+            (
+                "CONDA_PREFIX".to_string(),
+                prefix.to_string_lossy().into_owned(),
+            ),
+            ("PATH".to_string(), path),
+        ])
+        .collect()
+}
+
+/// Get the environment variables that are required to run a command in the prefix of the
+/// environment without running any activation scripts, on top of the variables of the current
+/// shell. The executable directories of the prefix are prepended to the `PATH` of the shell.
+pub(crate) fn get_inherited_prefix_environment_variables(
+    environment: &Environment<'_>,
+) -> HashMap<String, String> {
+    let prefix = environment.dir();
+    let current_path = std::env::var_os("PATH").unwrap_or_default();
+    let path = std::env::join_paths(
+        prefix_path_entries(&prefix)
+            .into_iter()
+            .chain(std::env::split_paths(&current_path)),
+    )
+    .map(|path| path.to_string_lossy().into_owned())
+    .unwrap_or_default();
+
+    std::env::vars()
+        .filter(|(key, _)| !key.eq_ignore_ascii_case("PATH"))
+        .chain(get_static_environment_variables(environment))
+        .chain([
+            (
+                "CONDA_PREFIX".to_string(),
+                prefix.to_string_lossy().into_owned(),
+            ),
+            ("PATH".to_string(), path),
+        ])
+        .collect()
+}
+
 /// Determine the environment variables that need to be set in an interactive shell to make it
 /// function as if the environment has been activated. This method runs the activation scripts from
 /// the environment and stores the environment variables it added, finally it adds environment
@@ -447,21 +570,33 @@ pub(crate) async fn initialize_env_variables(
 
     // Get environment variables from the currently activated shell.
     let current_shell_env_vars = match env_var_behavior {
-        CurrentEnvVarBehavior::Clean if cfg!(windows) => {
-            return Err(miette::miette!(
-                "Currently it's not possible to run a `clean-env` option on Windows."
-            ));
-        }
         CurrentEnvVarBehavior::Clean => get_clean_environment_variables(),
         CurrentEnvVarBehavior::Include => std::env::vars().collect(),
         CurrentEnvVarBehavior::Exclude => HashMap::new(),
     };
 
-    let all_variables: HashMap<String, String> = current_shell_env_vars
+    let mut all_variables: HashMap<String, String> = current_shell_env_vars
         .into_iter()
         .chain(activation_env)
         .collect();
 
+    // The activation replaced the `PATH` by the directories of the prefix, on
+    // Windows even the basic tools live outside of it so add the system
+    // directories back.
+    if cfg!(windows) && matches!(env_var_behavior, CurrentEnvVarBehavior::Clean) {
+        if let Some(path) = all_variables
+            .iter_mut()
+            .find_map(|(key, value)| key.eq_ignore_ascii_case("PATH").then_some(value))
+        {
+            let entries = std::env::split_paths(path.as_str())
+                .chain(system_path_entries())
+                .collect_vec();
+            if let Ok(joined) = std::env::join_paths(entries) {
+                *path = joined.to_string_lossy().into_owned();
+            }
+        }
+    }
+
     Ok(all_variables)
 }
 
@@ -581,6 +716,28 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(windows)]
+    fn test_windows_clean_environment_variables() {
+        let shell_env = HashMap::from(
+            [
+                ("SystemRoot", "C:\\Windows"),
+                ("windir", "C:\\Windows"),
+                ("ComSpec", "C:\\Windows\\system32\\cmd.exe"),
+                ("PATHEXT", ".COM;.EXE;.BAT;.CMD"),
+                ("PIXI_TEST_WINDOWS_CLEAN_ENV", "shell customization"),
+            ]
+            .map(|(key, value)| (key.to_string(), value.to_string())),
+        );
+        let env = clean_environment_variables(shell_env.clone());
+        // Make sure that the essential system variables are kept, whatever
+        // their case.
+        for key in ["SystemRoot", "windir", "ComSpec", "PATHEXT"] {
+            assert_eq!(env.get(key), shell_env.get(key), "{key} should be kept");
+        }
+        assert!(!env.contains_key("PIXI_TEST_WINDOWS_CLEAN_ENV"));
+    }
+
     /// Test that the activation cache is created and used correctly based on the lockfile.
     ///
     /// This test will validate the cache usages by running the activation script and checking if the cache is created.
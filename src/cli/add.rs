@@ -9,7 +9,7 @@ use rattler_conda_types::{MatchSpec, PackageName};
 use super::{cli_config::LockFileUpdateConfig, has_specs::HasSpecs};
 use crate::{
     cli::cli_config::{DependencyConfig, PrefixUpdateConfig, WorkspaceConfig},
-    environment::sanity_check_project,
+    environment::{sanity_check_project, verify_environment_prefix},
     workspace::DependencyType,
     WorkspaceLocator,
 };
@@ -108,6 +108,7 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         .with_cli_config(args.config.clone());
 
     sanity_check_project(&workspace).await?;
+    verify_environment_prefix(&workspace.default_environment())?;
 
     let mut workspace = workspace.modify()?;
 
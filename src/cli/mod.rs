@@ -231,6 +231,12 @@ pub async fn execute() -> miette::Result<()> {
         LevelFilter::TRACE => (LevelFilter::TRACE, LevelFilter::TRACE, LevelFilter::TRACE),
     };
 
+    // `pixi run --log-level` overrides the log level of pixi itself.
+    let pixi_level = match &args.command {
+        Command::Run(run_args) => run_args.log_level.map_or(pixi_level, LevelFilter::from),
+        _ => pixi_level,
+    };
+
     let env_filter = EnvFilter::builder()
         .with_default_directive(level_filter.into())
         .from_env()
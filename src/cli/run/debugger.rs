@@ -0,0 +1,109 @@
+//! Starts a task in a debugger, see `pixi run --gdb`.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// The debuggers that `pixi run --gdb` can start a task in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Debugger {
+    Gdb,
+    Lldb,
+}
+
+impl Debugger {
+    /// The debuggers in the order in which they are looked up, lldb is the
+    /// native debugger on macOS.
+    fn preferred() -> [Debugger; 2] {
+        if cfg!(target_os = "macos") {
+            [Debugger::Lldb, Debugger::Gdb]
+        } else {
+            [Debugger::Gdb, Debugger::Lldb]
+        }
+    }
+
+    fn executable_name(self) -> &'static str {
+        match self {
+            Debugger::Gdb => "gdb",
+            Debugger::Lldb => "lldb",
+        }
+    }
+
+    /// Returns the command that the command of a task is appended to, to
+    /// start it in the debugger at the given path.
+    ///
+    /// The path is single quoted. The task shell has no escapes outside of
+    /// double quotes, so a single quote in the path is double quoted instead.
+    pub(super) fn command_prefix(self, path: &Path) -> String {
+        let path = format!("'{}'", path.display().to_string().replace('\'', r#"'"'"'"#));
+        match self {
+            Debugger::Gdb => format!("{path} --args"),
+            Debugger::Lldb => format!("{path} --"),
+        }
+    }
+
+    /// Finds the preferred debugger in the `PATH` of the given environment.
+    pub(super) fn find(
+        command_env: &HashMap<String, String>,
+        cwd: &Path,
+    ) -> miette::Result<(Self, PathBuf)> {
+        let path = command_env
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("PATH"))
+            .map(|(_, value)| value);
+        Self::preferred()
+            .into_iter()
+            .find_map(|debugger| {
+                which::which_in(debugger.executable_name(), path, cwd)
+                    .ok()
+                    .map(|path| (debugger, path))
+            })
+            .ok_or_else(|| {
+                miette::miette!(
+                    help = "add gdb or lldb to the dependencies of the environment",
+                    "neither gdb nor lldb was found in the PATH of the environment"
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::run::parse_command;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_debugger() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let env = HashMap::from([("PATH".to_string(), tmp.path().display().to_string())]);
+        assert!(Debugger::find(&env, tmp.path()).is_err());
+
+        // The only available debugger is used, even if it is not preferred.
+        let [preferred, other] = Debugger::preferred();
+        let path = tmp.path().join(other.executable_name());
+        fs_err::write(&path, "").unwrap();
+        fs_err::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(Debugger::find(&env, tmp.path()).unwrap(), (other, path));
+
+        let path = tmp.path().join(preferred.executable_name());
+        fs_err::write(&path, "").unwrap();
+        fs_err::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(Debugger::find(&env, tmp.path()).unwrap(), (preferred, path));
+    }
+
+    #[test]
+    fn test_debugger_command_prefix_quotes_path() {
+        let path = Path::new("/opt/it's $HOME/gdb");
+        let prefix = Debugger::Gdb.command_prefix(path);
+        assert_eq!(prefix, r#"'/opt/it'"'"'s $HOME/gdb' --args"#);
+        assert!(parse_command(Some(&format!("{prefix} ./app"))).is_ok());
+        assert_eq!(
+            Debugger::Lldb.command_prefix(Path::new("/usr/bin/lldb")),
+            "'/usr/bin/lldb' --"
+        );
+    }
+}
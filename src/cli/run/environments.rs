@@ -0,0 +1,318 @@
+//! Computes the environment variables that tasks run with, once per
+//! environment of a `pixi run` invocation.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use async_once_cell::OnceCell as AsyncCell;
+use miette::MietteDiagnostic;
+use rattler_lock::LockFile;
+
+use super::{check_environment_drift, Args};
+use crate::{
+    lock_file::{LockFileDerivedData, ReinstallPackages},
+    task::{get_prefix_only_task_env, get_task_env, get_unactivated_task_env, ExecutableTask},
+    workspace::Environment,
+};
+
+/// The environment variable that holds the id of the `pixi run` invocation.
+const RUN_ID_ENV_VAR: &str = "PIXI_RUN_ID";
+
+/// The environment variables of the environments that tasks run in, shared
+/// between all tasks of a `pixi run` invocation.
+///
+/// The variables of an environment are computed at most once, tasks that
+/// request them while they are being computed wait for the result instead of
+/// activating the environment again. Every environment gets the same
+/// `PIXI_RUN_ID`, so the output of all tasks of an invocation can be
+/// correlated.
+#[derive(Clone)]
+pub(super) struct TaskEnvironments<'p> {
+    run_id: String,
+    envs: Arc<Mutex<HashMap<Environment<'p>, Arc<AsyncCell<Arc<HashMap<String, String>>>>>>>,
+}
+
+impl<'p> TaskEnvironments<'p> {
+    /// Creates an empty cache with a new random run id.
+    pub(super) fn new() -> Self {
+        Self {
+            run_id: uuid::Uuid::new_v4().to_string(),
+            envs: Default::default(),
+        }
+    }
+
+    /// Returns the id of this `pixi run` invocation.
+    pub(super) fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Returns the environment variables of `environment`, computing them with
+    /// `init` if they are not known yet.
+    async fn get_or_try_init(
+        &self,
+        environment: &Environment<'p>,
+        init: impl Future<Output = miette::Result<HashMap<String, String>>>,
+    ) -> miette::Result<Arc<HashMap<String, String>>> {
+        // Only hold the lock to get the cell, the computation happens outside
+        // of it so other environments are not blocked.
+        let cell = self
+            .envs
+            .lock()
+            .expect("task environments lock is poisoned")
+            .entry(environment.clone())
+            .or_default()
+            .clone();
+        let env = cell
+            .get_or_try_init(async {
+                let mut env = init.await?;
+                env.insert(RUN_ID_ENV_VAR.to_string(), self.run_id.clone());
+                Ok(Arc::new(env))
+            })
+            .await?;
+        Ok(env.clone())
+    }
+}
+
+/// Returns the environment variables that the given task is executed with,
+/// installing its environment if that didn't happen yet.
+pub(super) async fn task_environment<'p>(
+    args: &Args,
+    executable_task: &ExecutableTask<'p>,
+    lock_file: &mut LockFileDerivedData<'p>,
+    task_envs: &TaskEnvironments<'p>,
+) -> miette::Result<Arc<HashMap<String, String>>> {
+    task_envs
+        .get_or_try_init(&executable_task.run_environment, async {
+            // Ensure there is a valid prefix
+            let prefix = lock_file
+                .prefix(
+                    &executable_task.run_environment,
+                    args.prefix_update_config.update_mode(),
+                    ReinstallPackages::default(),
+                )
+                .await?;
+
+            if args.env_check {
+                check_environment_drift(
+                    &executable_task.run_environment,
+                    &lock_file.lock_file,
+                    &prefix,
+                    args.env_check_warn_only,
+                )?;
+            }
+
+            let command_env = get_command_env(args, executable_task, &lock_file.lock_file).await?;
+            check_required_env(&args.require_env, &args.require_env_message, &command_env)?;
+            Ok(command_env)
+        })
+        .await
+}
+
+/// Determines the environment variables that the given task is executed with.
+///
+/// The variables of `--require-env` are not checked here, `--warmup` and
+/// `--diff-env` don't run the task so they don't need them.
+pub(super) async fn get_command_env(
+    args: &Args,
+    executable_task: &ExecutableTask<'_>,
+    lock_file: &LockFile,
+) -> miette::Result<HashMap<String, String>> {
+    let clean_env = args.clean_env || executable_task.task().clean_env();
+    let mut command_env = if args.env_prefix_only {
+        get_prefix_only_task_env(&executable_task.run_environment)
+    } else if args.no_activation {
+        get_unactivated_task_env(&executable_task.run_environment, clean_env)
+    } else {
+        let workspace = executable_task.project();
+        get_task_env(
+            &executable_task.run_environment,
+            clean_env,
+            Some(lock_file),
+            workspace.config().force_activate(),
+            workspace.config().experimental_activation_cache_usage(),
+        )
+        .await?
+    };
+
+    // A clean environment leaves out the variables of the current shell, except
+    // for the ones that should explicitly be inherited.
+    if clean_env || args.env_prefix_only {
+        inherit_vars(
+            &mut command_env,
+            &args.inherit_vars,
+            &std::env::vars().collect(),
+        );
+    }
+
+    Ok(command_env)
+}
+
+/// Returns an error for the first variable of `--require-env` that is neither
+/// set in the current shell nor in the environment of the task. The help of the
+/// error is the matching message of `--require-env-message`, if any.
+fn check_required_env(
+    required: &[String],
+    messages: &[(String, String)],
+    command_env: &HashMap<String, String>,
+) -> miette::Result<()> {
+    let Some(key) = required
+        .iter()
+        .find(|key| !command_env.contains_key(*key) && std::env::var_os(key).is_none())
+    else {
+        return Ok(());
+    };
+
+    Err(MietteDiagnostic {
+        message: format!("the environment variable '{key}' is required by the task but not set"),
+        code: None,
+        severity: None,
+        help: messages
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, message)| message.clone()),
+        url: None,
+        labels: None,
+    }
+    .into())
+}
+
+/// Copies the given variables from the variables of the current shell into the
+/// environment, skipping the ones that are not set.
+fn inherit_vars(
+    command_env: &mut HashMap<String, String>,
+    vars: &[String],
+    shell_env: &HashMap<String, String>,
+) {
+    for var in vars {
+        if let Some(value) = shell_env.get(var) {
+            command_env.insert(var.clone(), value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::Path,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::cli::run::harness;
+
+    #[tokio::test]
+    async fn test_task_environments_are_computed_once() {
+        let workspace = harness::workspace(Path::new("pixi.toml"), "");
+        let environment = workspace.default_environment();
+        let task_envs = TaskEnvironments::new();
+        let computed = AtomicUsize::new(0);
+        let compute = || async {
+            computed.fetch_add(1, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            Ok(HashMap::from([("FOO".to_string(), "bar".to_string())]))
+        };
+
+        // Two tasks in the same environment request the variables concurrently
+        let (first, second) = tokio::join!(
+            task_envs.get_or_try_init(&environment, compute()),
+            task_envs.get_or_try_init(&environment, compute()),
+        );
+        assert_eq!(first.unwrap(), second.unwrap());
+
+        // A later task reuses the variables as well
+        task_envs
+            .get_or_try_init(&environment, compute())
+            .await
+            .unwrap();
+        assert_eq!(computed.load(Ordering::SeqCst), 1);
+
+        // The variables include the id of the run
+        let env = task_envs
+            .get_or_try_init(&environment, compute())
+            .await
+            .unwrap();
+        assert_eq!(env.get("FOO").unwrap(), "bar");
+        assert_eq!(env.get(RUN_ID_ENV_VAR).unwrap(), task_envs.run_id());
+        assert!(uuid::Uuid::parse_str(task_envs.run_id()).is_ok());
+    }
+
+    #[test]
+    fn test_inherit_vars() {
+        let shell_env = HashMap::from([
+            (
+                "PIXI_TEST_INHERITED_VAR".to_string(),
+                "inherited".to_string(),
+            ),
+            ("PIXI_TEST_OTHER_VAR".to_string(), "other".to_string()),
+        ]);
+
+        let mut command_env = HashMap::from([("FOO".to_string(), "bar".to_string())]);
+        inherit_vars(
+            &mut command_env,
+            &[
+                "PIXI_TEST_INHERITED_VAR".to_string(),
+                "PIXI_TEST_MISSING_VAR".to_string(),
+            ],
+            &shell_env,
+        );
+
+        assert_eq!(
+            command_env,
+            HashMap::from([
+                ("FOO".to_string(), "bar".to_string()),
+                (
+                    "PIXI_TEST_INHERITED_VAR".to_string(),
+                    "inherited".to_string()
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_check_required_env() {
+        let key = "PIXI_TEST_REQUIRED_ENV_NOT_SET";
+        let required = vec![key.to_string()];
+        let messages = vec![(key.to_string(), "run 'login' first".to_string())];
+
+        // A variable that is set by the environment of the task is enough.
+        let command_env = HashMap::from([(key.to_string(), "set".to_string())]);
+        check_required_env(&required, &messages, &command_env).unwrap();
+
+        let err = check_required_env(&required, &messages, &HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains(key));
+        assert_eq!(err.help().unwrap().to_string(), "run 'login' first");
+
+        // Variables of the current shell are always available.
+        let required = vec!["PATH".to_string()];
+        check_required_env(&required, &[], &HashMap::new()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_command_env_ignores_required_env() {
+        let workspace = harness::workspace(
+            Path::new("pixi.toml"),
+            r#"
+        [tasks]
+        build = "echo build"
+    "#,
+        );
+        let task = harness::planned_task(&workspace, "build");
+        let key = "PIXI_TEST_REQUIRED_ENV_WARMUP";
+        let args = Args {
+            require_env: vec![key.to_string()],
+            no_activation: true,
+            ..Args::default()
+        };
+
+        // `--warmup` and `--diff-env` only determine the environment of the
+        // task, a missing required variable only fails the run of the task.
+        let command_env = get_command_env(&args, &task, &LockFile::default())
+            .await
+            .unwrap();
+        assert!(!command_env.contains_key(key));
+        assert!(check_required_env(&args.require_env, &[], &command_env).is_err());
+    }
+}
@@ -0,0 +1,39 @@
+//! The workspaces and tasks that the tests of `pixi run` run against.
+
+use std::path::Path;
+
+use super::planned_tasks;
+use crate::{
+    task::{ExecutableTask, SearchEnvironments, TaskGraph},
+    Workspace,
+};
+
+const PROJECT_BOILERPLATE: &str = r#"
+[project]
+name = "pixi"
+channels = []
+platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+"#;
+
+/// Returns the workspace at `manifest_path` whose manifest consists of the
+/// project boilerplate followed by `tables`.
+pub(super) fn workspace(manifest_path: &Path, tables: &str) -> Workspace {
+    Workspace::from_str(manifest_path, &format!("{PROJECT_BOILERPLATE}\n{tables}")).unwrap()
+}
+
+/// Returns the task graph of the task `name`, looked up in `environment` or
+/// in the default environment.
+pub(super) fn task_graph<'p>(
+    workspace: &'p Workspace,
+    environment: Option<&str>,
+    name: &str,
+) -> TaskGraph<'p> {
+    let environment = environment.map(|name| workspace.environment(name).unwrap());
+    let search_envs = SearchEnvironments::from_opt_env(workspace, environment, None);
+    TaskGraph::from_cmd_args(workspace, &search_envs, vec![name.to_string()], false).unwrap()
+}
+
+/// Returns the first task that runs for the task `name`.
+pub(super) fn planned_task<'p>(workspace: &'p Workspace, name: &str) -> ExecutableTask<'p> {
+    planned_tasks(&task_graph(workspace, None, name), false).remove(0)
+}
@@ -0,0 +1,183 @@
+//! Runs the hooks of `pixi run --on-success` and `pixi run --on-failure`.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use deno_task_shell::{execute_with_pipes, ShellPipeReader, ShellPipeWriter, ShellState};
+
+use super::ExecuteTaskOptions;
+use crate::task::ExecutableTask;
+
+/// The task whose outcome is passed to the `--on-success` and `--on-failure`
+/// hooks.
+pub(super) struct HookContext {
+    task_name: String,
+    environment_name: String,
+    env: Arc<HashMap<String, String>>,
+    cwd: PathBuf,
+}
+
+impl HookContext {
+    pub(super) fn new(
+        task: &ExecutableTask<'_>,
+        env: Arc<HashMap<String, String>>,
+        options: &ExecuteTaskOptions<'_>,
+    ) -> Self {
+        let workspace = task.project();
+        Self {
+            task_name: task.name().unwrap_or_default().to_string(),
+            environment_name: task.run_environment.name().to_string(),
+            env,
+            cwd: options
+                .run_in
+                .map_or_else(|| workspace.root().to_path_buf(), Path::to_path_buf),
+        }
+    }
+
+    /// Runs the `--on-success` or `--on-failure` hook, depending on the exit
+    /// code of the task. Returns the exit code of the hook, or `None` if no
+    /// hook is configured.
+    pub(super) async fn run_hook(
+        &self,
+        options: &ExecuteTaskOptions<'_>,
+        exit_code: i32,
+    ) -> Option<i32> {
+        let hook = if exit_code == 0 {
+            options.on_success.as_ref()
+        } else {
+            options.on_failure.as_ref()
+        }?;
+
+        let mut env = (*self.env).clone();
+        env.insert("PIXI_TASK_EXIT_CODE".to_string(), exit_code.to_string());
+        env.insert("PIXI_TASK_NAME".to_string(), self.task_name.clone());
+        env.insert("PIXI_TASK_ENV".to_string(), self.environment_name.clone());
+        let state = ShellState::new(env, &self.cwd, Default::default(), Default::default());
+        let status_code = execute_with_pipes(
+            hook.clone(),
+            state,
+            ShellPipeReader::stdin(),
+            ShellPipeWriter::stdout(),
+            ShellPipeWriter::stderr(),
+        )
+        .await;
+        if status_code != 0 {
+            tracing::warn!("the hook exited with a non-zero exit code {status_code}");
+        }
+        Some(status_code)
+    }
+}
+
+/// Runs the `--on-failure` hook for the first failed task, or otherwise the
+/// `--on-success` hook for the last task, once all environments finished.
+/// Returns the exit code of the failed task.
+pub(super) async fn run_final_hook(
+    options: &ExecuteTaskOptions<'_>,
+    first_failure: Option<(HookContext, i32)>,
+    last_task: Option<HookContext>,
+) -> Option<i32> {
+    match (first_failure, last_task) {
+        (Some((context, code)), _) => {
+            context.run_hook(options, code).await;
+            Some(code)
+        }
+        (None, Some(last_task)) => {
+            last_task.run_hook(options, 0).await;
+            None
+        }
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::run::{harness, parse_command, planned_tasks};
+
+    #[tokio::test]
+    async fn test_run_hooks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workspace = harness::workspace(
+            &tmp.path().join("pixi.toml"),
+            r#"
+        [tasks]
+        build = "echo build"
+    "#,
+        );
+        let task = harness::planned_task(&workspace, "build");
+
+        let options = ExecuteTaskOptions {
+            run_in: Some(tmp.path()),
+            on_success: parse_command(Some(
+                "echo $PIXI_TASK_NAME $PIXI_TASK_ENV $PIXI_TASK_EXIT_CODE > success.txt",
+            ))
+            .unwrap(),
+            on_failure: parse_command(Some(
+                "echo $PIXI_TASK_NAME $PIXI_TASK_ENV $PIXI_TASK_EXIT_CODE > failure.txt",
+            ))
+            .unwrap(),
+            ..Default::default()
+        };
+        let context = HookContext::new(&task, Arc::default(), &options);
+        let read = |name: &str| fs_err::read_to_string(tmp.path().join(name)).ok();
+
+        // A passing run only fires the success hook
+        assert_eq!(context.run_hook(&options, 0).await, Some(0));
+        assert_eq!(read("success.txt").as_deref(), Some("build default 0\n"));
+        assert_eq!(read("failure.txt"), None);
+
+        // A failing run only fires the failure hook
+        fs_err::remove_file(tmp.path().join("success.txt")).unwrap();
+        assert_eq!(context.run_hook(&options, 3).await, Some(0));
+        assert_eq!(read("failure.txt").as_deref(), Some("build default 3\n"));
+        assert_eq!(read("success.txt"), None);
+
+        // Without hooks nothing runs
+        let context = HookContext::new(&task, Arc::default(), &Default::default());
+        assert_eq!(context.run_hook(&Default::default(), 3).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_final_hook_runs_once_for_all_environments() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workspace = harness::workspace(
+            &tmp.path().join("pixi.toml"),
+            r#"
+        [feature.a.tasks]
+        test = "exit 2"
+        [feature.b.tasks]
+        test = "exit 3"
+        [environments]
+        a = ["a"]
+        b = ["b"]
+    "#,
+        );
+        let options = ExecuteTaskOptions {
+            run_in: Some(tmp.path()),
+            on_success: parse_command(Some("echo success >> hooks.txt")).unwrap(),
+            on_failure: parse_command(Some(
+                "echo $PIXI_TASK_ENV $PIXI_TASK_EXIT_CODE >> hooks.txt",
+            ))
+            .unwrap(),
+            ..Default::default()
+        };
+
+        // The task fails in both environments, like the loop in `execute`
+        // collects them.
+        let mut first_failure = None;
+        for (environment, code) in [("a", 2), ("b", 3)] {
+            let task_graph = harness::task_graph(&workspace, Some(environment), "test");
+            let task = planned_tasks(&task_graph, false).remove(0);
+            first_failure.get_or_insert((HookContext::new(&task, Arc::default(), &options), code));
+        }
+
+        assert_eq!(run_final_hook(&options, first_failure, None).await, Some(2));
+        assert_eq!(
+            fs_err::read_to_string(tmp.path().join("hooks.txt")).unwrap(),
+            "a 2\n"
+        );
+    }
+}
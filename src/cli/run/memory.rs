@@ -0,0 +1,164 @@
+//! Measures the peak memory usage of a task, see `pixi run --measure-memory`.
+//!
+//! The processes started by pixi are sampled while the task runs. The peak of
+//! a sample is the sum of the peak resident set sizes of the processes that
+//! are alive at that moment, the peak of the task is the largest sample.
+
+use std::{
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// How often the processes of a task are sampled.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Whether the memory usage of tasks can be measured on this platform.
+pub(super) const SUPPORTED: bool = cfg!(target_os = "linux");
+
+/// Samples the memory usage of the processes started by pixi until it is
+/// stopped.
+pub(super) struct PeakMemory {
+    stop: Sender<()>,
+    sampler: JoinHandle<u64>,
+}
+
+impl PeakMemory {
+    /// Starts sampling the processes started by pixi in a background thread.
+    pub(super) fn start() -> Self {
+        let (stop, stopped) = mpsc::channel();
+        let sampler = std::thread::spawn(move || {
+            let mut peak = 0;
+            loop {
+                peak = peak.max(process_tree_peak(std::process::id()).unwrap_or(0));
+                match stopped.recv_timeout(SAMPLE_INTERVAL) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Ok(()) | Err(RecvTimeoutError::Disconnected) => return peak,
+                }
+            }
+        });
+        Self { stop, sampler }
+    }
+
+    /// Stops sampling and returns the peak memory usage in bytes, or `None` if
+    /// no process was seen.
+    pub(super) fn stop(self) -> Option<u64> {
+        let _ = self.stop.send(());
+        let peak = self.sampler.join().ok()?;
+        (peak > 0).then_some(peak)
+    }
+}
+
+/// Returns the sum of the peak resident set sizes in bytes of the processes
+/// that descend from the process `root`.
+#[cfg(target_os = "linux")]
+fn process_tree_peak(root: u32) -> Option<u64> {
+    let parents = std::fs::read_dir("/proc")
+        .ok()?
+        .filter_map(|entry| {
+            let pid = entry.ok()?.file_name().to_str()?.parse::<u32>().ok()?;
+            let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+            Some((pid, parse_parent_pid(&stat)?))
+        })
+        .collect::<Vec<_>>();
+    let peak = descendants(&parents, root)
+        .into_iter()
+        .filter_map(|pid| {
+            let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+            parse_peak_rss(&status)
+        })
+        .sum();
+    Some(peak)
+}
+
+/// Measuring the memory usage of tasks is not supported on this platform.
+#[cfg(not(target_os = "linux"))]
+fn process_tree_peak(_root: u32) -> Option<u64> {
+    None
+}
+
+/// Returns the processes that descend from `root`, given the `(pid, parent)`
+/// pairs of all processes.
+fn descendants(parents: &[(u32, u32)], root: u32) -> Vec<u32> {
+    let mut found = vec![root];
+    let mut next = 0;
+    while let Some(&parent) = found.get(next) {
+        found.extend(
+            parents
+                .iter()
+                .filter(|(pid, ppid)| *ppid == parent && !found.contains(pid))
+                .map(|(pid, _)| *pid)
+                .collect::<Vec<_>>(),
+        );
+        next += 1;
+    }
+    found.remove(0);
+    found
+}
+
+/// Parses the parent process id from the contents of `/proc/<pid>/stat`.
+///
+/// The name of the process comes before the parent and can contain spaces and
+/// parentheses, so the fields are read after the last closing parenthesis.
+fn parse_parent_pid(stat: &str) -> Option<u32> {
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Parses the peak resident set size (`VmHWM`) in bytes from the contents of
+/// `/proc/<pid>/status`.
+fn parse_peak_rss(status: &str) -> Option<u64> {
+    let line = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?;
+    let kilobytes = line.trim().strip_suffix("kB")?.trim().parse::<u64>().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_files() {
+        assert_eq!(
+            parse_parent_pid("4242 (my (odd) tool) S 17 4242 17 0 -1 4194560"),
+            Some(17)
+        );
+        assert_eq!(parse_parent_pid("garbage"), None);
+
+        let status =
+            "Name:\tpython\nVmPeak:\t  300000 kB\nVmHWM:\t    1234 kB\nVmRSS:\t     900 kB\n";
+        assert_eq!(parse_peak_rss(status), Some(1234 * 1024));
+        assert_eq!(parse_peak_rss("Name:\tkthreadd\n"), None);
+    }
+
+    #[test]
+    fn test_descendants() {
+        let parents = [(2, 1), (3, 2), (4, 3), (5, 1), (6, 9), (7, 5)];
+        assert_eq!(descendants(&parents, 2), vec![3, 4]);
+        assert_eq!(descendants(&parents, 1), vec![2, 5, 3, 7, 4]);
+        assert_eq!(descendants(&parents, 6), Vec::<u32>::new());
+    }
+
+    /// Every measurement only covers the processes that ran while it was
+    /// active, a small task after a large one reports its own peak.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_peak_memory_per_task() {
+        let measure = |script: &str| {
+            let memory = PeakMemory::start();
+            std::process::Command::new("sh")
+                .args(["-c", script])
+                .status()
+                .unwrap();
+            memory.stop()
+        };
+
+        let large = measure("x=$(head -c 50000000 /dev/zero | tr '\\0' a); sleep 0.3")
+            .expect("the shell was sampled");
+        assert!(large > 50_000_000, "{large}");
+        let small = measure("sleep 0.3").expect("the shell was sampled");
+        assert!(small < 50_000_000, "{small}");
+    }
+}
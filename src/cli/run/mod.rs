@@ -0,0 +1,3060 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    convert::identity,
+    hash::{Hash, Hasher},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    string::String,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use clap::{Parser, ValueEnum};
+use deno_task_shell::{
+    execute_with_pipes, parser::SequentialList, pipe, ShellPipeReader, ShellPipeWriter, ShellState,
+};
+use dialoguer::theme::ColorfulTheme;
+use fancy_display::FancyDisplay;
+use futures::StreamExt;
+use indicatif::HumanBytes;
+use itertools::{Either, Itertools};
+use miette::{Context, Diagnostic, IntoDiagnostic, NamedSource, Report};
+use pixi_config::{ConfigCli, ConfigCliActivation};
+use pixi_glob::GlobHashCache;
+use pixi_manifest::{
+    task::Custom,
+    toml::{FromTomlStr, TomlTasksDocument},
+    EnvironmentName, FeaturesExt, TaskName,
+};
+use rattler_conda_types::PackageRecord;
+use rattler_lock::LockFile;
+use regex::Regex;
+use thiserror::Error;
+use tracing::Level;
+use tracing_subscriber::filter::LevelFilter;
+use url::Url;
+use wax::{Glob, Program};
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::{
+    cli::cli_config::{PrefixUpdateConfig, WorkspaceConfig},
+    diff::{LockFileDiff, PackagesDiff},
+    environment::{sanity_check_project, verify_environment_prefix},
+    lock_file::{
+        LockFileDerivedData, OutdatedEnvironments, ReinstallPackages, UpdateLockFileOptions,
+    },
+    prefix::Prefix,
+    task::{
+        AmbiguousTask, CanSkip, EnvMergeConflict, ExecutableTask, FailedToParseShellScript,
+        InvalidWorkingDirectory, SearchEnvironments, TaskAndEnvironment, TaskDisambiguation,
+        TaskGraph, TaskGraphError, TaskHash, TaskId, TaskPrecedence,
+    },
+    workspace::{
+        errors::UnsupportedPlatformError,
+        virtual_packages::verify_current_platform_can_run_environment, Environment,
+    },
+    Workspace, WorkspaceLocator,
+};
+
+use self::{
+    debugger::Debugger,
+    environments::{get_command_env, task_environment, TaskEnvironments},
+    hooks::{run_final_hook, HookContext},
+    output::{
+        capturing_writer, open_log_file, output_writer, tap_output_writer, LogFile, TaskOutcome,
+        TaskReport,
+    },
+    remote::{fetch_remote_manifest, remote_task},
+};
+use super::{cli_config::LockFileUpdateConfig, task::parse_key_val};
+
+mod debugger;
+mod environments;
+#[cfg(test)]
+mod harness;
+mod hooks;
+mod memory;
+mod output;
+mod remote;
+
+pub use self::output::OutputFormat;
+
+/// Runs task in the pixi environment.
+///
+/// This command is used to run tasks in the pixi environment.
+/// It will activate the environment and run the task in the environment.
+/// It is using the deno_task_shell to run the task.
+///
+/// `pixi run` will also update the lockfile and install the environment if it is required.
+#[derive(Parser, Debug, Default)]
+#[clap(trailing_var_arg = true, disable_help_flag = true)]
+pub struct Args {
+    /// The pixi task or a task shell command you want to run in the workspace's
+    /// environment, which can be an executable in the environment's PATH.
+    pub task: Vec<String>,
+
+    #[clap(flatten)]
+    pub workspace_config: WorkspaceConfig,
+
+    #[clap(flatten)]
+    pub prefix_update_config: PrefixUpdateConfig,
+
+    #[clap(flatten)]
+    pub lock_file_update_config: LockFileUpdateConfig,
+
+    #[clap(flatten)]
+    pub config: ConfigCli,
+
+    #[clap(flatten)]
+    pub activation_config: ConfigCliActivation,
+
+    /// The environment to run the task in.
+    ///
+    /// This can also be a glob pattern (e.g. `py3*`) to run the task in every
+    /// environment whose name matches.
+    #[arg(long, short)]
+    pub environment: Option<String>,
+
+    /// Select the environment from the current directory
+    ///
+    /// The path of the current directory relative to the workspace root is
+    /// searched, from the innermost directory outwards, for a directory that
+    /// has the name of an environment. For example, running from
+    /// `<workspace>/backend/src` selects the `backend` environment. If no
+    /// directory matches, the environment is selected as usual.
+    #[arg(long, conflicts_with = "environment")]
+    pub environment_from_cwd: bool,
+
+    /// Run the task in every environment of the workspace
+    ///
+    /// Environments that don't support the current platform, or in which the
+    /// task is not available on it, are skipped. All environments run even if
+    /// the task fails in some of them, the exit code is the exit code of the
+    /// first failure.
+    #[arg(long, conflicts_with_all = ["environment", "environment_from_cwd"])]
+    pub environment_all: bool,
+
+    /// Use a clean environment to run the task
+    ///
+    /// Using this flag will ignore your current shell environment and use bare
+    /// minimum environment to activate the pixi environment in.
+    #[arg(long)]
+    pub clean_env: bool,
+
+    /// Variables to inherit from the current shell when the task runs in a
+    /// clean environment, e.g. `--inherit-vars AWS_PROFILE,KUBECONFIG`
+    ///
+    /// Variables that are not set in the current shell are skipped.
+    #[arg(long, value_delimiter = ',', value_name = "KEY")]
+    pub inherit_vars: Vec<String>,
+
+    /// Fail before the task runs if the given environment variable is not set
+    ///
+    /// The variable can be set in the current shell or by the environment of
+    /// the task. May be provided more than once.
+    #[arg(long, value_name = "KEY")]
+    pub require_env: Vec<String>,
+
+    /// The help that is shown when a variable of `--require-env` is not set,
+    /// e.g. `--require-env-message AWS_PROFILE="run 'aws sso login' first"`
+    #[arg(long, value_name = "KEY=MESSAGE", value_parser = parse_key_val, requires = "require_env")]
+    pub require_env_message: Vec<(String, String)>,
+
+    /// Don't run the dependencies of the task ('depends-on' field in the task
+    /// definition)
+    #[arg(long)]
+    pub skip_deps: bool,
+
+    /// Run the task in dry-run mode (only print the command that would run)
+    #[clap(short = 'n', long)]
+    pub dry_run: bool,
+
+    /// Print the execution plan without running it: the tasks in the order in
+    /// which they would run, with their environment, command and cache status
+    #[arg(long, conflicts_with = "dry_run")]
+    pub plan: bool,
+
+    /// Install and activate the environments required by the task without
+    /// running any task
+    ///
+    /// This is useful to separate the installation from the execution phase,
+    /// e.g. for better caching in CI.
+    #[arg(long, conflicts_with_all = ["dry_run", "plan"])]
+    pub warmup: bool,
+
+    /// Print the difference between the current shell environment and the
+    /// environment the task would run in, without running the task
+    #[arg(long, conflicts_with_all = ["dry_run", "plan", "warmup"])]
+    pub diff_env: bool,
+
+    /// Compare the conda packages installed in the environment with the lock
+    /// file before the task runs, and fail if they differ
+    ///
+    /// Packages that were removed, added or updated outside of pixi, e.g. by
+    /// conda or mamba, are reported.
+    #[arg(long)]
+    pub env_check: bool,
+
+    /// Only warn about the differences found by `--env-check` instead of
+    /// failing
+    #[arg(long, requires = "env_check")]
+    pub env_check_warn_only: bool,
+
+    /// Only put the executables of the environment on the PATH without
+    /// running its activation scripts
+    ///
+    /// The task runs with a minimal environment: the PATH only contains the
+    /// executable directories of the environment and the system directories,
+    /// and only a minimal set of conda and pixi variables is set. This is
+    /// faster and more deterministic than a full activation.
+    #[arg(long)]
+    pub env_prefix_only: bool,
+
+    /// Run the tasks without running the activation scripts of the
+    /// environment
+    ///
+    /// The executables of the environment are put in front of the PATH of the
+    /// current shell, whose variables are kept. Together with `--clean-env`
+    /// this is the same as `--env-prefix-only`. This is faster for tasks that
+    /// don't depend on the activation scripts.
+    #[arg(long, conflicts_with = "env_prefix_only")]
+    pub no_activation: bool,
+
+    /// Set the log level of pixi itself, a shorthand for
+    /// `RUST_LOG=pixi=<LOG_LEVEL>`
+    ///
+    /// This overrides the level of pixi set through `RUST_LOG` or the global
+    /// verbosity flags.
+    #[arg(long, value_enum)]
+    pub log_level: Option<LogLevel>,
+
+    /// Run the tasks in the given directory instead of their own working
+    /// directory
+    ///
+    /// The workspace is still discovered from the current directory (or
+    /// `--manifest-path`), only the directory the commands are executed in
+    /// changes. This is useful to run a task in a subproject or a git worktree.
+    #[arg(long, value_name = "PATH")]
+    pub run_in: Option<PathBuf>,
+
+    /// Where the stdin of the tasks is connected to
+    ///
+    /// `inherit` passes the stdin of pixi through, `null` connects it to an
+    /// empty input so that tasks never block on reading it, and `tty`
+    /// connects it to the terminal even if the stdin of pixi is redirected,
+    /// e.g. for tasks that call `sudo`.
+    #[arg(long, value_enum, default_value_t)]
+    pub stdin_passthrough: StdinPassthrough,
+
+    /// Read the stdin of the tasks from the given file
+    ///
+    /// Every task reads the file from the start. This makes runs of tasks
+    /// that read their stdin reproducible.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["stdin_passthrough", "task_stdin_string"])]
+    pub task_stdin: Option<PathBuf>,
+
+    /// Pass the given string as the stdin of the tasks
+    #[arg(long, value_name = "STRING", conflicts_with = "stdin_passthrough")]
+    pub task_stdin_string: Option<String>,
+
+    /// Don't run the `default` task when no task is given, list the available
+    /// tasks instead
+    #[arg(long)]
+    pub no_default: bool,
+
+    /// Fail if the outputs of a cached task were modified outside of pixi
+    ///
+    /// When the inputs of a task are unchanged since its last run but its
+    /// outputs differ, the build is not hermetic. By default the task is
+    /// simply executed again.
+    #[arg(long)]
+    pub check_outputs: bool,
+
+    /// Also write the output of the tasks to the given file, while still
+    /// streaming it to the terminal
+    ///
+    /// The file is truncated unless `--log-file-append` is given.
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Append to the file given with `--log-file` instead of truncating it
+    #[arg(long, requires = "log_file")]
+    pub log_file_append: bool,
+
+    /// Store the task cache in the given directory instead of the `.pixi`
+    /// directory of the workspace
+    ///
+    /// A subdirectory named after a hash of the workspace root is used, so
+    /// that multiple workspaces can share the same directory. The directory
+    /// must be writable. Also available as `--cache-dir`.
+    #[arg(long, value_name = "PATH", visible_alias = "cache-dir")]
+    pub task_cache_dir: Option<PathBuf>,
+
+    /// Run a task defined in a remote manifest instead of the workspace
+    ///
+    /// The first argument is the name of the task in the remote manifest. The
+    /// task runs in the environment of the current workspace, so the remote
+    /// manifest can't declare dependencies. The task must define a command
+    /// and can only set a description; `depends-on`, `cwd`, `env` and the
+    /// other task fields are not supported. Only HTTP and HTTPS URLs are
+    /// supported.
+    #[arg(long, value_name = "URL")]
+    pub task_url: Option<Url>,
+
+    /// The expected SHA256 hash of the remote manifest given with
+    /// `--task-url`
+    #[arg(long, value_name = "SHA256", requires = "task_url")]
+    pub expected_hash: Option<String>,
+
+    /// Print a summary of the changes to the lock-file when it was updated
+    /// before running the task
+    #[arg(long)]
+    pub print_lockfile_changes: bool,
+
+    /// Pipe the output of the tasks through the given command
+    ///
+    /// The stdout of every task is connected to the stdin of the command,
+    /// e.g. `--pipe-through "tee -a logfile.txt"`. The exit code is still
+    /// determined by the task, regardless of the exit code of the command.
+    #[arg(long, value_name = "CMD")]
+    pub pipe_through: Option<String>,
+
+    /// Print a completion script for the tasks of the workspace instead of
+    /// running a task
+    ///
+    /// Task names are read from the manifest, so they can't be part of the
+    /// completion generated by `pixi completion`. Source the output in your
+    /// shell to complete the task name after `pixi run`.
+    #[arg(long, value_name = "SHELL")]
+    pub generate_completion: Option<CompletionShell>,
+
+    /// Run the tasks in reverse order, every task runs before its
+    /// dependencies
+    ///
+    /// This is useful for teardown tasks that mirror the dependencies of a
+    /// setup task, e.g. stopping services in the reverse order in which they
+    /// were started. The task cache is not used, tasks always run.
+    #[arg(long, conflicts_with = "check_outputs")]
+    pub reverse: bool,
+
+    /// Load additional tasks from the given TOML file
+    ///
+    /// The file only contains a `[tasks]` table. Its tasks can depend on the
+    /// tasks of the workspace and the other way around, but they can't
+    /// redefine them. May be provided more than once.
+    #[arg(long, value_name = "PATH")]
+    pub task_graph_file: Vec<PathBuf>,
+
+    /// Create a temporary directory for every task, its path is passed to the
+    /// task in `PIXI_TASK_TEMPDIR`
+    ///
+    /// The directory is removed after the task finished, also when it failed.
+    /// Tasks can also request one with `tempdir = true` in the manifest.
+    #[arg(long)]
+    pub tempdir: bool,
+
+    /// Run the given command after all tasks succeeded
+    ///
+    /// The command runs in the environment of the last task, with
+    /// `PIXI_TASK_NAME`, `PIXI_TASK_ENV` and `PIXI_TASK_EXIT_CODE` set. Its
+    /// exit code doesn't change the exit code of `pixi run`.
+    #[arg(long, value_name = "CMD")]
+    pub on_success: Option<String>,
+
+    /// Run the given command after a task failed
+    ///
+    /// The command runs in the environment of the failed task, with
+    /// `PIXI_TASK_NAME`, `PIXI_TASK_ENV` and `PIXI_TASK_EXIT_CODE` set. Its
+    /// exit code doesn't change the exit code of `pixi run`.
+    #[arg(long, value_name = "CMD")]
+    pub on_failure: Option<String>,
+
+    /// Verify that the task could run without installing any environment or
+    /// running the task
+    ///
+    /// Checks that the lock-file is up-to-date, that the task and all its
+    /// dependencies exist without forming a cycle, that all `inputs` and
+    /// `outputs` are valid globs and that the environments can run on this
+    /// platform. Exits with a non-zero exit code if any check fails.
+    #[arg(long, conflicts_with_all = ["dry_run", "plan", "warmup", "diff_env"])]
+    pub check: bool,
+
+    /// The format in which the results of the tasks are reported on stdout
+    ///
+    /// `json` prints a JSON object with the run id, name, environment, status
+    /// and exit code of every task after it finished. `tap` prints a Test
+    /// Anything Protocol stream in which the stdout of the tasks becomes `# `
+    /// comments, followed by the `1..N` plan.
+    #[arg(long, value_enum, default_value_t)]
+    pub output_format: OutputFormat,
+
+    /// Don't print emoji in the output of pixi, colours are kept
+    ///
+    /// Useful for terminals that render emoji as broken characters. Emoji are
+    /// also left out when the terminal doesn't support them.
+    #[arg(long)]
+    pub no_emoji: bool,
+
+    /// Print the peak memory usage of every task after it finished
+    ///
+    /// This is the peak resident set size of the processes started by the
+    /// task, sampled while the task runs. Only supported on Linux.
+    #[arg(long)]
+    pub measure_memory: bool,
+
+    /// Run the task once for every file that matches the given glob
+    ///
+    /// The glob is matched against the workspace root and the path of the file
+    /// is passed to the task in `PIXI_EACH_FILE`. The dependencies of the task
+    /// still run once, and the task cache is not used for the task itself.
+    /// All files are processed even if the task fails for some of them. May
+    /// be provided more than once.
+    #[arg(long, value_name = "GLOB")]
+    pub each: Vec<String>,
+
+    /// The number of files that are processed at the same time with `--each`,
+    /// defaults to one file at a time
+    #[arg(long, value_name = "N", requires = "each")]
+    pub each_jobs: Option<NonZeroUsize>,
+
+    /// Where the arguments after the task name end up in the command of the
+    /// task
+    ///
+    /// With `cli` they are appended to the command, so they override the
+    /// arguments in the manifest for most tools. With `manifest` they are
+    /// inserted directly after the program of the last command, so the
+    /// arguments in the manifest come last; this fails if the last command is
+    /// a pipeline or a subshell. With `cli-replaces` they replace the command
+    /// of the task.
+    #[arg(long, value_enum, default_value_t)]
+    pub task_precedence: TaskPrecedence,
+
+    /// Print the hash that the task cache uses for the task and its
+    /// dependencies without running them
+    ///
+    /// The hash covers the command, the `inputs` and `outputs` files and the
+    /// packages of the environment. A task is skipped when its hash equals the
+    /// hash of its last successful run, comparing hashes shows why a task is
+    /// or isn't skipped.
+    #[arg(long, conflicts_with_all = ["dry_run", "plan", "warmup", "diff_env", "check"])]
+    pub print_inputs_hash: bool,
+
+    /// Fail if the stdout of a task doesn't match the given regular expression
+    ///
+    /// The output is still printed while the task runs. With `--pipe-through`
+    /// the output of the command it is piped through is matched. May be
+    /// provided more than once, the output has to match all patterns.
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    pub assert_output: Vec<Regex>,
+
+    /// Run the task in a debugger
+    ///
+    /// The command of the task is started with `gdb --args`, or with `lldb --`
+    /// on macOS. The debugger is looked up in the `PATH` of the environment,
+    /// so it can be installed as a dependency of the workspace. Only the
+    /// requested task runs in the debugger, its dependencies run as usual.
+    #[arg(long, conflicts_with_all = ["clean_env", "each"])]
+    pub gdb: bool,
+
+    #[clap(long, action = clap::ArgAction::HelpLong)]
+    pub help: Option<bool>,
+
+    #[clap(short, action = clap::ArgAction::HelpShort)]
+    pub h: Option<bool>,
+}
+
+/// The log levels that can be passed to `pixi run --log-level`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Trace => LevelFilter::TRACE,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Error => LevelFilter::ERROR,
+        }
+    }
+}
+
+/// Where the stdin of a task is connected to, see `pixi run
+/// --stdin-passthrough`.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StdinPassthrough {
+    Null,
+    #[default]
+    Inherit,
+    Tty,
+}
+
+impl StdinPassthrough {
+    /// Returns the reader to use as the stdin of a task.
+    fn reader(self) -> Result<ShellPipeReader, TaskExecutionError> {
+        let path = Path::new(match self {
+            StdinPassthrough::Inherit => return Ok(ShellPipeReader::stdin()),
+            StdinPassthrough::Null if cfg!(windows) => "NUL",
+            StdinPassthrough::Null => "/dev/null",
+            StdinPassthrough::Tty if cfg!(windows) => "CONIN$",
+            StdinPassthrough::Tty => "/dev/tty",
+        });
+        open_stdin_file(path)
+    }
+}
+
+/// Where the stdin of a task is read from, see `--stdin-passthrough`,
+/// `--task-stdin` and `--task-stdin-string`.
+#[derive(Debug, Clone)]
+enum TaskStdin {
+    Passthrough(StdinPassthrough),
+    File(PathBuf),
+    String(Arc<str>),
+}
+
+impl Default for TaskStdin {
+    fn default() -> Self {
+        TaskStdin::Passthrough(StdinPassthrough::default())
+    }
+}
+
+impl TaskStdin {
+    /// Determines the stdin of the tasks from the command line arguments.
+    fn from_args(args: &Args) -> Self {
+        if let Some(path) = &args.task_stdin {
+            TaskStdin::File(path.clone())
+        } else if let Some(content) = &args.task_stdin_string {
+            TaskStdin::String(content.as_str().into())
+        } else {
+            TaskStdin::Passthrough(args.stdin_passthrough)
+        }
+    }
+
+    /// Returns the reader to use as the stdin of a task.
+    fn reader(&self) -> Result<ShellPipeReader, TaskExecutionError> {
+        match self {
+            TaskStdin::Passthrough(passthrough) => passthrough.reader(),
+            TaskStdin::File(path) => open_stdin_file(path),
+            TaskStdin::String(content) => {
+                // Write from a separate thread, so that a task that doesn't
+                // read its stdin can't block pixi.
+                let (reader, mut writer) = pipe();
+                let content = content.clone();
+                std::thread::spawn(move || {
+                    if let Err(err) = writer.write_all(content.as_bytes()) {
+                        tracing::debug!("the task did not read all of its stdin: {err}");
+                    }
+                });
+                Ok(reader)
+            }
+        }
+    }
+}
+
+/// Opens the file at `path` as the stdin of a task.
+fn open_stdin_file(path: &Path) -> Result<ShellPipeReader, TaskExecutionError> {
+    let file = std::fs::File::open(path)
+        .map_err(|err| TaskExecutionError::OpenStdin(path.to_path_buf(), err))?;
+    Ok(ShellPipeReader::from_std(file))
+}
+
+/// The shells for which `pixi run --generate-completion` can print a task
+/// completion script.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl CompletionShell {
+    /// Returns a script that completes the given tasks as the first argument
+    /// of `pixi run`.
+    fn task_completion_script(self, bin_name: &str, tasks: &[String]) -> String {
+        let function_name = format!("__{}_run_completing", bin_name.replace('-', "_"));
+        match self {
+            // `compgen -W` splits the words again, so the tasks are matched in a
+            // loop and the completions are quoted with `printf %q`.
+            CompletionShell::Bash => format!(
+                r#"{function_name}() {{
+    if [[ ${{COMP_CWORD}} -eq 2 && ( "${{COMP_WORDS[1]}}" == "run" || "${{COMP_WORDS[1]}}" == "r" ) ]]; then
+        local task tasks=({tasks})
+        COMPREPLY=()
+        for task in "${{tasks[@]}}"; do
+            [[ "$task" == "${{COMP_WORDS[COMP_CWORD]}}"* ]] && COMPREPLY+=("$(printf '%q' "$task")")
+        done
+        return 0
+    fi
+    declare -F _{bin_name} > /dev/null && _{bin_name} "$@"
+}}
+complete -F {function_name} -o bashdefault -o default {bin_name}
+"#,
+                tasks = tasks.iter().map(|task| posix_quote(task)).join(" "),
+            ),
+            // A colon separates the description of a task for `_describe`.
+            CompletionShell::Zsh => format!(
+                r#"{function_name}() {{
+    local -a tasks
+    tasks=({tasks})
+    if (( CURRENT == 3 )) && [[ "${{words[2]}}" == (run|r) ]]; then
+        _describe 'task' tasks
+    else
+        (( $+functions[_{bin_name}] )) && _{bin_name} "$@"
+    fi
+}}
+compdef {function_name} {bin_name}
+"#,
+                tasks = tasks
+                    .iter()
+                    .map(|task| posix_quote(&task.replace(':', "\\:")))
+                    .join(" "),
+            ),
+            // The arguments of `complete -a` are split again, the lines of a
+            // command substitution are not.
+            CompletionShell::Fish => format!(
+                r#"function {function_name}
+    set -l tokens (commandline -opc)
+    test (count $tokens) -eq 2; and contains -- $tokens[2] run r
+end
+function {function_name}_tasks
+    printf '%s\n' {tasks}
+end
+complete -c {bin_name} -n '{function_name}' -f -a '({function_name}_tasks)'
+"#,
+                tasks = tasks
+                    .iter()
+                    .map(|task| format!("'{}'", task.replace('\\', "\\\\").replace('\'', "\\'")))
+                    .join(" "),
+            ),
+            CompletionShell::Powershell => format!(
+                r#"Register-ArgumentCompleter -Native -CommandName '{bin_name}' -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $elements = $commandAst.CommandElements
+    $completingTask = $elements.Count -eq 2 -or ($elements.Count -eq 3 -and $wordToComplete)
+    if ($elements.Count -ge 2 -and @('run', 'r') -contains $elements[1].ToString() -and $completingTask) {{
+        @({tasks}) | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
+            [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+        }}
+    }}
+}}
+"#,
+                tasks = tasks
+                    .iter()
+                    .map(|task| format!("'{}'", task.replace('\'', "''")))
+                    .join(", "),
+            ),
+        }
+    }
+}
+
+/// Quotes a string for a POSIX shell, in single quotes in which only the
+/// single quote itself has to be escaped.
+fn posix_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// CLI entry point for `pixi run`
+/// When running the sigints are ignored and child can react to them. As it
+/// pleases.
+pub async fn execute(mut args: Args) -> miette::Result<()> {
+    NO_EMOJI.store(args.no_emoji, Ordering::Relaxed);
+
+    let cli_config = args
+        .activation_config
+        .clone()
+        .merge_config(args.config.clone().into());
+
+    // Load the workspace
+    let mut workspace = WorkspaceLocator::for_cli()
+        .with_search_start(args.workspace_config.workspace_locator_start())
+        .locate()?
+        .with_cli_config(cli_config);
+    add_task_graph_files(&mut workspace, &args.task_graph_file)?;
+
+    // Print the completion of the tasks without running anything.
+    if let Some(shell) = args.generate_completion {
+        let tasks = available_tasks(&workspace, None)
+            .into_keys()
+            .map(|task| task.to_string())
+            .collect_vec();
+        print!(
+            "{}",
+            shell.task_completion_script(pixi_utils::executable_name(), &tasks)
+        );
+        return Ok(());
+    }
+
+    // Find the environments to run the task in, if any were specified.
+    let environment = if args.environment_from_cwd {
+        let cwd = std::env::current_dir().into_diagnostic()?;
+        environment_name_from_dir(&workspace, &cwd)
+    } else {
+        args.environment.clone()
+    };
+    let explicit_environments = if args.environment_all {
+        workspace
+            .environments()
+            .into_iter()
+            .sorted_by(|a, b| a.name().as_str().cmp(b.name().as_str()))
+            .map(Some)
+            .collect_vec()
+    } else {
+        explicit_environments(&workspace, environment)?
+    };
+
+    // Validate the directory to run the tasks in and open the log file before
+    // anything is executed.
+    let run_in = args.run_in.as_deref().map(run_in_directory).transpose()?;
+    let log_file = args
+        .log_file
+        .as_deref()
+        .map(|path| open_log_file(path, args.log_file_append))
+        .transpose()?;
+
+    let each_files = if args.each.is_empty() {
+        Vec::new()
+    } else {
+        each_files(&workspace, &args.each)?
+    };
+
+    let pipe_through = parse_command(args.pipe_through.as_deref())?;
+    let on_success = parse_command(args.on_success.as_deref())?;
+    let on_failure = parse_command(args.on_failure.as_deref())?;
+
+    // The remote task runs instead of a task of the workspace.
+    let remote_task = match &args.task_url {
+        Some(url) => {
+            let Some(name) = args.task.first() else {
+                miette::bail!("the name of the task to run from '{url}' is missing");
+            };
+            let content = fetch_remote_manifest(&workspace, url).await?;
+            Some(remote_task(&content, args.expected_hash.as_deref(), name)?)
+        }
+        None => None,
+    };
+
+    // Run the default task if no task is provided, like `make` does.
+    if args.task.is_empty()
+        && !args.no_default
+        && has_default_task(&workspace, &explicit_environments)
+    {
+        args.task = vec![DEFAULT_TASK.to_string()];
+    }
+
+    // Print all available tasks if no task is provided
+    if args.task.is_empty() {
+        command_not_found(
+            &workspace,
+            explicit_environments.into_iter().flatten().next(),
+        );
+        return Ok(());
+    }
+
+    // Only verify that the task could run.
+    if args.check {
+        return check_tasks(
+            &args,
+            &workspace,
+            &explicit_environments,
+            remote_task.as_ref(),
+        )
+        .await;
+    }
+
+    // Sanity check of prefix location
+    sanity_check_project(&workspace).await?;
+    for environment in &explicit_environments {
+        let environment = environment
+            .clone()
+            .unwrap_or_else(|| workspace.default_environment());
+        verify_environment_prefix(&environment)?;
+    }
+
+    // Remember the lock-file before it is updated to report the changes.
+    let original_lock_file = if args.print_lockfile_changes {
+        Some(workspace.load_lock_file().await?)
+    } else {
+        None
+    };
+
+    // Ensure that the lock-file is up-to-date.
+    let mut lock_file = workspace
+        .update_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.lock_file_update_config.lock_file_usage(),
+            max_concurrent_solves: workspace.config().max_concurrent_solves(),
+            ..UpdateLockFileOptions::default()
+        })
+        .await?;
+
+    if let Some(original_lock_file) = original_lock_file {
+        let diff = LockFileDiff::from_lock_files(&original_lock_file, &lock_file.lock_file);
+        if let Some(summary) = lock_file_changes_summary(&diff) {
+            eprintln!("{summary}");
+            diff.print()
+                .into_diagnostic()
+                .context("failed to print lock-file diff")?;
+        }
+    }
+
+    // dialoguer doesn't reset the cursor if it's aborted via e.g. SIGINT
+    // So we do it ourselves.
+    let ctrlc_should_exit_process = &CTRLC_SHOULD_EXIT_PROCESS;
+    ctrlc_should_exit_process.store(true, Ordering::Relaxed);
+    install_ctrlc_handler()?;
+
+    // Execute the task graph once for every selected environment. The
+    // environment variables of an environment are shared between the runs.
+    let task_cache_dir = args
+        .task_cache_dir
+        .as_deref()
+        .map(|dir| task_cache_dir(&workspace, dir))
+        .transpose()?;
+    let task_envs = TaskEnvironments::new();
+    tracing::info!("Run id: {}", task_envs.run_id());
+    let options = ExecuteTaskOptions {
+        run_in: run_in.as_deref(),
+        stdin: TaskStdin::from_args(&args),
+        log_file,
+        task_cache_dir,
+        task_precedence: args.task_precedence,
+        pipe_through,
+        tempdir: args.tempdir,
+        on_success,
+        on_failure,
+        report: TaskReport::new(args.output_format, task_envs.run_id().to_string()),
+        each_files,
+        each_jobs: args.each_jobs.map_or(1, NonZeroUsize::get),
+        assert_output: args.assert_output.clone(),
+        remote_task,
+    };
+    if args.measure_memory && !memory::SUPPORTED {
+        miette::bail!("measuring the memory usage of tasks with `--measure-memory` is only supported on Linux");
+    }
+    let explicit_environments = if args.environment_all {
+        let task = options.remote_task.is_none().then(|| args.task[0].as_str());
+        let (environments, skipped) = environments_for_task(&workspace, task);
+        for (environment, reason) in skipped {
+            eprintln!(
+                "{}Skipping environment {}: {reason}",
+                pixi_emoji("⏭️ ", ""),
+                environment.name().fancy_display()
+            );
+        }
+        environments.into_iter().map(Some).collect_vec()
+    } else {
+        explicit_environments
+    };
+    let mut last_task = None;
+    let mut first_failure = None;
+    let mut failures = Vec::new();
+    for explicit_environment in explicit_environments {
+        let name = explicit_environment
+            .as_ref()
+            .filter(|_| args.environment_all)
+            .map(|environment| environment.name().clone());
+        if let Some(name) = &name {
+            eprintln!("{}", environment_banner(name));
+        }
+        match execute_task_graph(
+            &args,
+            &workspace,
+            explicit_environment,
+            &mut lock_file,
+            &task_envs,
+            &options,
+            ctrlc_should_exit_process,
+        )
+        .await
+        {
+            Ok(TaskGraphOutcome::Succeeded(task)) => last_task = task.or(last_task),
+            Ok(TaskGraphOutcome::Failed(context, code)) => {
+                failures.push((name.unwrap_or_default(), code));
+                first_failure.get_or_insert((context, code));
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    options.report.finish();
+
+    if !failures.is_empty() {
+        eprintln!(
+            "{}The task failed in {}",
+            console::style(pixi_emoji("❌ ", "")).red(),
+            failures
+                .iter()
+                .map(|(name, code)| format!("{} (exit code {code})", name.fancy_display()))
+                .join(", ")
+        );
+    }
+    if let Some(code) = run_final_hook(&options, first_failure, last_task).await {
+        std::process::exit(code);
+    }
+
+    Ok(())
+}
+
+/// Verifies that the tasks could run without installing an environment or
+/// executing anything, see `pixi run --check`. Every check is printed with its
+/// result.
+async fn check_tasks(
+    args: &Args,
+    workspace: &Workspace,
+    explicit_environments: &[Option<Environment<'_>>],
+    remote_task: Option<&Custom>,
+) -> miette::Result<()> {
+    let mut failed = 0;
+    let mut report = |description: &str, result: Result<(), String>| match result {
+        Ok(()) => eprintln!(
+            "{}{description}",
+            console::style(pixi_emoji("✔ ", "+ ")).green()
+        ),
+        Err(details) => {
+            failed += 1;
+            eprintln!(
+                "{}{description}\n  {}",
+                console::style(pixi_emoji("✗ ", "x ")).red(),
+                details.replace('\n', "\n  ")
+            );
+        }
+    };
+
+    report(
+        &format!("The workspace '{}' loads", workspace.name()),
+        Ok(()),
+    );
+
+    let lock_file = match workspace.load_lock_file().await {
+        Ok(lock_file) => {
+            let outdated = OutdatedEnvironments::from_workspace_and_lock_file(
+                workspace,
+                &lock_file,
+                GlobHashCache::default(),
+            )
+            .await;
+            let outdated_envs = outdated
+                .conda
+                .keys()
+                .chain(outdated.pypi.keys())
+                .map(|env| env.name().to_string())
+                .collect::<BTreeSet<_>>();
+            report(
+                "The lock-file is up-to-date",
+                if outdated_envs.is_empty() {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "the environments {} are out of date, run `pixi lock` to update them",
+                        outdated_envs.iter().join(", ")
+                    ))
+                },
+            );
+            Some(lock_file)
+        }
+        Err(err) => {
+            report("The lock-file is up-to-date", Err(err.to_string()));
+            None
+        }
+    };
+
+    for explicit_environment in explicit_environments {
+        let best_platform = explicit_environment
+            .as_ref()
+            .map_or_else(|| workspace.default_environment(), Clone::clone)
+            .best_platform();
+        let search_environment = SearchEnvironments::from_opt_env(
+            workspace,
+            explicit_environment.clone(),
+            Some(best_platform),
+        );
+
+        // The remaining checks need the task graph.
+        let task_graph = match task_graph(args, workspace, &search_environment, remote_task) {
+            Ok(task_graph) => {
+                report("The task and its dependencies exist", Ok(()));
+                report("The inputs and outputs are valid globs", Ok(()));
+                task_graph
+            }
+            Err(TaskGraphError::InvalidGlobs(err)) => {
+                report("The task and its dependencies exist", Ok(()));
+                report(
+                    "The inputs and outputs are valid globs",
+                    Err(err.errors.iter().join("\n")),
+                );
+                continue;
+            }
+            Err(err) => {
+                report("The task and its dependencies exist", Err(err.to_string()));
+                continue;
+            }
+        };
+
+        report(
+            "The dependencies of the task don't form a cycle",
+            match task_graph.find_cycle() {
+                None => Ok(()),
+                Some(cycle) => Err(cycle
+                    .into_iter()
+                    .map(|id| {
+                        task_graph[id]
+                            .name
+                            .as_ref()
+                            .map_or("", |name| name.as_str())
+                    })
+                    .join(" -> ")),
+            },
+        );
+
+        let environments = task_graph
+            .topological_order()
+            .into_iter()
+            .map(|id| task_graph[id].run_environment.clone())
+            .unique()
+            .collect_vec();
+        for environment in environments {
+            report(
+                &format!(
+                    "The environment '{}' can run on {}",
+                    environment.name(),
+                    environment.best_platform()
+                ),
+                verify_current_platform_can_run_environment(&environment, lock_file.as_ref())
+                    .map_err(|err| err.to_string()),
+            );
+        }
+    }
+
+    if failed > 0 {
+        miette::bail!("{failed} check(s) failed");
+    }
+    Ok(())
+}
+
+/// Parses a command that is passed on the command line.
+fn parse_command(
+    command: Option<&str>,
+) -> Result<Option<SequentialList>, FailedToParseShellScript> {
+    command
+        .map(|command| {
+            deno_task_shell::parser::parse(command.trim()).map_err(|e| FailedToParseShellScript {
+                script: command.to_string(),
+                error: e.to_string(),
+            })
+        })
+        .transpose()
+}
+
+/// Adds the tasks of the files passed to `--task-graph-file` to the default
+/// feature of the workspace. The workspace is only modified in memory.
+fn add_task_graph_files(workspace: &mut Workspace, paths: &[PathBuf]) -> miette::Result<()> {
+    for path in paths {
+        let content = fs_err::read_to_string(path).into_diagnostic()?;
+        let document = TomlTasksDocument::from_toml_str(&content).map_err(|err| {
+            Report::from(err).with_source_code(NamedSource::new(
+                path.display().to_string(),
+                content.clone(),
+            ))
+        })?;
+        for warning in document.warnings {
+            tracing::warn!("{:?}", Report::from(warning));
+        }
+
+        let manifest = &mut workspace.workspace.value;
+        for (name, task) in document.tasks {
+            let already_defined = manifest
+                .features
+                .values()
+                .flat_map(|feature| feature.targets.targets())
+                .any(|target| target.tasks.contains_key(&name));
+            if already_defined {
+                miette::bail!(
+                    help = "rename the task in one of the files",
+                    "the task '{}' from '{}' is already defined",
+                    name.fancy_display(),
+                    path.display()
+                );
+            }
+            manifest
+                .get_or_insert_target_mut(None, None)
+                .tasks
+                .insert(name, task);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the environments that were explicitly selected to run the task in,
+/// or a single `None` if the task should run in the environment it is defined
+/// in.
+///
+/// The environment can be selected by name, through the `PIXI_ENVIRONMENT_NAME`
+/// environment variable or with a glob pattern (e.g. `py3*`) that selects all
+/// environments whose name matches.
+fn explicit_environments(
+    workspace: &Workspace,
+    environment: Option<String>,
+) -> miette::Result<Vec<Option<Environment<'_>>>> {
+    if let Some(pattern) = environment.as_deref().filter(|name| is_glob_pattern(name)) {
+        let glob = Glob::new(pattern).into_diagnostic()?;
+        let environments = workspace
+            .environments()
+            .into_iter()
+            .filter(|env| glob.is_match(env.name().as_str()))
+            .sorted_by(|a, b| a.name().as_str().cmp(b.name().as_str()))
+            .map(Some)
+            .collect_vec();
+        if environments.is_empty() {
+            miette::bail!("no environment matches the pattern '{pattern}'");
+        }
+        return Ok(environments);
+    }
+
+    // Extract the passed in environment name.
+    let is_explicit = environment.is_some();
+    let environment = workspace.environment_from_name_or_env_var(environment)?;
+    if !is_explicit && environment.is_default() {
+        Ok(vec![None])
+    } else {
+        Ok(vec![Some(environment)])
+    }
+}
+
+/// Splits the environments of the workspace into the environments that
+/// `--environment-all` runs the task in and the environments that are skipped,
+/// together with the reason why.
+///
+/// A task that is not defined in any environment is a shell command, which runs
+/// in every environment that supports the current platform. So is a custom
+/// command, which is passed as `None`.
+fn environments_for_task<'p>(
+    workspace: &'p Workspace,
+    task: Option<&str>,
+) -> (Vec<Environment<'p>>, Vec<(Environment<'p>, String)>) {
+    let name = task.map(TaskName::from);
+    let is_task = |environment: &Environment<'_>| {
+        name.as_ref().is_some_and(|name| {
+            environment
+                .task(name, Some(environment.best_platform()))
+                .is_ok()
+        })
+    };
+    let environments = workspace
+        .environments()
+        .into_iter()
+        .sorted_by(|a, b| a.name().as_str().cmp(b.name().as_str()))
+        .collect_vec();
+    let is_shell_command = !environments.iter().any(&is_task);
+
+    environments.into_iter().partition_map(|environment| {
+        let platform = environment.best_platform();
+        if !environment.platforms().contains(&platform) {
+            Either::Right((
+                environment,
+                format!("the environment doesn't support {platform}"),
+            ))
+        } else if !is_shell_command && !is_task(&environment) {
+            Either::Right((
+                environment,
+                format!("the task '{task}' is not available on {platform}"),
+            ))
+        } else {
+            Either::Left(environment)
+        }
+    })
+}
+
+/// Returns the line that is printed before the task runs in an environment
+/// with `--environment-all`.
+fn environment_banner(name: &EnvironmentName) -> String {
+    format!(
+        "{}{}",
+        console::style("Environment ").bold(),
+        name.fancy_display()
+    )
+}
+
+/// Constructs the task graph of the task on the command line. The task of
+/// `--task-url` is a custom command, so its command is never mistaken for a
+/// task of the workspace.
+fn task_graph<'p, D: TaskDisambiguation<'p>>(
+    args: &Args,
+    workspace: &'p Workspace,
+    search_environment: &SearchEnvironments<'p, D>,
+    remote_task: Option<&Custom>,
+) -> Result<TaskGraph<'p>, TaskGraphError> {
+    match remote_task {
+        Some(remote_task) => Ok(TaskGraph::from_custom(
+            workspace,
+            search_environment,
+            remote_task.clone(),
+            args.task.iter().skip(1).cloned().collect(),
+        )),
+        None => TaskGraph::from_cmd_args(
+            workspace,
+            search_environment,
+            args.task.clone(),
+            args.skip_deps,
+        ),
+    }
+}
+
+/// The name of the task that runs when `pixi run` is invoked without a task.
+const DEFAULT_TASK: &str = "default";
+
+/// Returns true if the [`DEFAULT_TASK`] is available in one of the given
+/// environments.
+fn has_default_task(workspace: &Workspace, environments: &[Option<Environment<'_>>]) -> bool {
+    let default_task = TaskName::from(DEFAULT_TASK);
+    environments
+        .iter()
+        .any(|env| available_tasks(workspace, env.clone()).contains_key(&default_task))
+}
+
+/// Returns the name of the environment that matches the innermost directory of
+/// `dir` relative to the workspace root, see `pixi run --environment-from-cwd`.
+fn environment_name_from_dir(workspace: &Workspace, dir: &Path) -> Option<String> {
+    let relative = dir.strip_prefix(workspace.root()).ok()?;
+    relative
+        .components()
+        .rev()
+        .filter_map(|component| component.as_os_str().to_str())
+        .find(|name| workspace.environment(*name).is_some())
+        .map(ToString::to_string)
+}
+
+/// Returns true if the environment argument is a glob pattern instead of a
+/// plain environment name.
+fn is_glob_pattern(name: &str) -> bool {
+    name.contains(['*', '?', '['])
+}
+
+/// The outcome of executing the task graph in an environment.
+enum TaskGraphOutcome {
+    /// All tasks succeeded, with the context of the last task for the
+    /// `--on-success` hook if there is one.
+    Succeeded(Option<HookContext>),
+
+    /// A task exited with a non-zero exit code. Only returned with
+    /// `--environment-all`, which runs the `--on-failure` hook once all
+    /// environments finished.
+    Failed(HookContext, i32),
+}
+
+/// Constructs the task graph for the given explicit environment and executes
+/// it.
+async fn execute_task_graph<'p>(
+    args: &Args,
+    workspace: &'p Workspace,
+    explicit_environment: Option<Environment<'p>>,
+    lock_file: &mut LockFileDerivedData<'p>,
+    task_envs: &TaskEnvironments<'p>,
+    options: &ExecuteTaskOptions<'_>,
+    ctrlc_should_exit_process: &AtomicBool,
+) -> miette::Result<TaskGraphOutcome> {
+    let best_platform = explicit_environment
+        .as_ref()
+        .map_or_else(|| workspace.default_environment(), Clone::clone)
+        .best_platform();
+
+    // Construct a task graph from the input arguments
+    let search_environment = SearchEnvironments::from_opt_env(
+        workspace,
+        explicit_environment.clone(),
+        Some(best_platform),
+    )
+    .with_disambiguate_fn(disambiguate_task_interactive);
+
+    let task_graph = task_graph(
+        args,
+        workspace,
+        &search_environment,
+        options.remote_task.as_ref(),
+    )?;
+
+    tracing::info!("Task graph: {}", task_graph);
+
+    if !options.each_files.is_empty() && !task_graph[task_graph.root()].task.is_executable() {
+        miette::bail!("`--each` requires a task with a command, not an alias");
+    }
+
+    // Print the execution plan and exit if plan mode is enabled
+    if args.plan {
+        print_task_plan(&task_graph, &lock_file.lock_file, options, args.reverse).await?;
+        return Ok(TaskGraphOutcome::Succeeded(None));
+    }
+
+    // Print the hashes of the task cache and exit.
+    if args.print_inputs_hash {
+        for executable_task in planned_tasks(&task_graph, args.reverse) {
+            let executable_task = executable_task.with_task_precedence(options.task_precedence);
+            println!(
+                "{}",
+                inputs_hash_line(&executable_task, &lock_file.lock_file).await?
+            );
+        }
+        return Ok(TaskGraphOutcome::Succeeded(None));
+    }
+
+    // Only prepare the environments of the task graph if warmup mode is enabled
+    if args.warmup {
+        let mut warmed_up_envs = Vec::new();
+        for executable_task in planned_tasks(&task_graph, args.reverse) {
+            let environment = &executable_task.run_environment;
+            if warmed_up_envs.contains(environment) {
+                continue;
+            }
+            lock_file
+                .prefix(
+                    environment,
+                    args.prefix_update_config.update_mode(),
+                    ReinstallPackages::default(),
+                )
+                .await?;
+            get_command_env(&args, &executable_task, &lock_file.lock_file).await?;
+            warmed_up_envs.push(environment.clone());
+        }
+        eprintln!(
+            "{}Warmed up environments: {}",
+            console::style(pixi_emoji("✔ ", "")).green(),
+            warmed_up_envs
+                .iter()
+                .map(|env| env.name().fancy_display())
+                .join(", ")
+        );
+        return Ok(TaskGraphOutcome::Succeeded(None));
+    }
+
+    // Only print the environment differences if diff-env mode is enabled
+    if args.diff_env {
+        let mut diffed_envs = Vec::new();
+        for executable_task in planned_tasks(&task_graph, args.reverse) {
+            let environment = &executable_task.run_environment;
+            if diffed_envs.contains(environment) {
+                continue;
+            }
+            lock_file
+                .prefix(
+                    environment,
+                    args.prefix_update_config.update_mode(),
+                    ReinstallPackages::default(),
+                )
+                .await?;
+            let command_env =
+                get_command_env(&args, &executable_task, &lock_file.lock_file).await?;
+            let clean_env =
+                args.clean_env || args.env_prefix_only || executable_task.task().clean_env();
+            print_env_diff(environment, &command_env, clean_env);
+            diffed_envs.push(environment.clone());
+        }
+        return Ok(TaskGraphOutcome::Succeeded(None));
+    }
+
+    // Print dry-run message if dry-run mode is enabled
+    if args.dry_run {
+        eprintln!(
+            "{}{}",
+            pixi_emoji("🌵 ", ""),
+            console::style("Dry-run mode enabled - no tasks will be executed.")
+                .yellow()
+                .bold(),
+        );
+        eprintln!();
+    }
+
+    // Traverse the task graph in topological order and execute each individual
+    // task.
+    let mut task_idx = 0;
+    let mut last_task_id = None;
+    for task_id in execution_order(&task_graph, args.reverse) {
+        let executable_task = ExecutableTask::from_task_graph(&task_graph, task_id)
+            .with_task_cache_dir(options.task_cache_dir.clone())
+            .with_task_precedence(options.task_precedence);
+
+        // If the task is not executable (e.g. an alias), we skip it. This ensures we
+        // don't instantiate a prefix for an alias.
+        if !executable_task.task().is_executable() {
+            continue;
+        }
+
+        // Showing which command is being run if the level and type allows it.
+        if tracing::enabled!(Level::WARN) && !executable_task.task().is_custom() {
+            if task_idx > 0 {
+                // Add a newline between task outputs
+                eprintln!();
+            }
+            eprintln!(
+                "{}{}{}{}{}{}{}",
+                pixi_emoji("✨ ", ""),
+                console::style("Pixi task (").bold(),
+                console::style(executable_task.name().unwrap_or("unnamed"))
+                    .green()
+                    .bold(),
+                // Only print environment if multiple environments are available
+                if workspace.environments().len() > 1 {
+                    format!(
+                        " in {}",
+                        executable_task.run_environment.name().fancy_display()
+                    )
+                } else {
+                    "".to_string()
+                },
+                console::style("): ").bold(),
+                executable_task.display_command(),
+                if let Some(description) = executable_task.task().description() {
+                    console::style(format!(": ({})", description)).yellow()
+                } else {
+                    console::style("".to_string()).yellow()
+                }
+            );
+        }
+
+        // on dry-run mode, we just print the command and skip the execution
+        if args.dry_run {
+            task_idx += 1;
+            continue;
+        }
+        last_task_id = Some(task_id);
+
+        // Fail before the environment is installed if it can't run here.
+        check_platform_support(&executable_task)?;
+
+        // With `--each` the requested task runs once per file.
+        let each = task_id == task_graph.root() && !options.each_files.is_empty();
+        // With `--gdb` the requested task runs in a debugger.
+        let debug = task_id == task_graph.root() && args.gdb;
+
+        // check task cache, in reverse order the tasks undo the work of their
+        // dependencies so the cache doesn't apply. The cache doesn't track the
+        // files of `--each` either, and a task is always run in the debugger.
+        let task_cache = match if args.reverse || each || debug {
+            CanSkip::No(None)
+        } else {
+            executable_task
+                .can_skip(&lock_file.lock_file)
+                .await
+                .into_diagnostic()?
+        } {
+            CanSkip::No(Some(hash)) if args.check_outputs => {
+                if executable_task
+                    .outputs_modified(&hash)
+                    .await
+                    .into_diagnostic()?
+                {
+                    miette::bail!(
+                        help = "run the task without `--check-outputs` to recreate the outputs",
+                        "the outputs of task '{}' were modified outside of pixi",
+                        executable_task.name().unwrap_or("")
+                    );
+                }
+                Some(hash)
+            }
+            CanSkip::No(cache) => cache,
+            CanSkip::Yes(reason) => {
+                eprintln!(
+                    "Task '{}' can be skipped (cache hit){}",
+                    console::style(executable_task.name().unwrap_or("")).bold(),
+                    pixi_emoji(" 🚀", "")
+                );
+                if tracing::enabled!(Level::INFO) {
+                    eprintln!("  {}", console::style(reason).dim());
+                }
+                options
+                    .report
+                    .task_finished(&executable_task, TaskOutcome::Skipped, None);
+                task_idx += 1;
+                continue;
+            }
+        };
+
+        // If we don't have a command environment yet, we need to compute it. We lazily
+        // compute the task environment because we only need the environment if
+        // a task is actually executed.
+        let task_env = task_environment(args, &executable_task, lock_file, task_envs).await?;
+
+        let executable_task = if debug {
+            let (debugger, path) = Debugger::find(&task_env, executable_task.project().root())?;
+            executable_task.with_command_prefix(debugger.command_prefix(&path))
+        } else {
+            executable_task
+        };
+
+        ctrlc_should_exit_process.store(false, Ordering::Relaxed);
+
+        let memory = args.measure_memory.then(memory::PeakMemory::start);
+
+        // Execute the task itself within the command environment. If one of the tasks
+        // failed with a non-zero exit code, we exit this parent process with
+        // the same code.
+        let result = if each {
+            execute_task_each(&executable_task, &task_env, options).await
+        } else {
+            execute_task(&executable_task, &task_env, options).await
+        };
+        let peak_rss = memory.and_then(memory::PeakMemory::stop);
+        if let Some(peak_rss) = peak_rss {
+            eprintln!("Peak memory: {}", HumanBytes(peak_rss));
+        }
+        match result {
+            Ok(_) => {
+                options
+                    .report
+                    .task_finished(&executable_task, TaskOutcome::Ok, peak_rss);
+                task_idx += 1;
+            }
+            Err(TaskExecutionError::NonZeroExitCode(code)) => {
+                options
+                    .report
+                    .task_finished(&executable_task, TaskOutcome::Failed(code), peak_rss);
+                if !args.environment_all {
+                    options.report.finish();
+                }
+                if code == 127 {
+                    command_not_found(workspace, explicit_environment);
+                }
+                let context = HookContext::new(&executable_task, task_env, options);
+                // With `--environment-all` the task still runs in the other
+                // environments.
+                if args.environment_all {
+                    return Ok(TaskGraphOutcome::Failed(context, code));
+                }
+                context.run_hook(options, code).await;
+                std::process::exit(code);
+            }
+            Err(err) => {
+                options
+                    .report
+                    .task_finished(&executable_task, TaskOutcome::Failed(1), peak_rss);
+                options.report.finish();
+                HookContext::new(&executable_task, task_env, options)
+                    .run_hook(options, 1)
+                    .await;
+                return Err(err.into());
+            }
+        }
+
+        // Handle CTRL-C ourselves again
+        ctrlc_should_exit_process.store(true, Ordering::Relaxed);
+
+        // Update the task cache with the new hash
+        if !args.reverse && !each && !debug {
+            executable_task
+                .save_cache(&lock_file, task_cache)
+                .await
+                .into_diagnostic()?;
+        }
+    }
+
+    // The success hook runs in the environment of the last task, which is not
+    // known yet if that task was skipped.
+    let Some(task_id) = last_task_id.filter(|_| options.on_success.is_some()) else {
+        return Ok(TaskGraphOutcome::Succeeded(None));
+    };
+    let executable_task = ExecutableTask::from_task_graph(&task_graph, task_id);
+    let task_env = task_environment(args, &executable_task, lock_file, task_envs).await?;
+    Ok(TaskGraphOutcome::Succeeded(Some(HookContext::new(
+        &executable_task,
+        task_env,
+        options,
+    ))))
+}
+
+/// Verifies that the environment of the task supports the current platform.
+fn check_platform_support(task: &ExecutableTask<'_>) -> Result<(), UnsupportedPlatformError> {
+    let environment = &task.run_environment;
+    let platform = environment.best_platform();
+    if environment.platforms().contains(&platform) {
+        return Ok(());
+    }
+    Err(UnsupportedPlatformError {
+        environments_platforms: environment.platforms().into_iter().collect(),
+        environment: environment.name().clone(),
+        platform,
+        task: task.name().map(TaskName::from),
+    })
+}
+
+/// Returns the order in which the tasks of the task graph are executed, with
+/// `--reverse` every task runs before its dependencies.
+fn execution_order(task_graph: &TaskGraph<'_>, reverse: bool) -> Vec<TaskId> {
+    let mut order = task_graph.topological_order();
+    if reverse {
+        order.reverse();
+    }
+    order
+}
+
+/// Returns the executable tasks of the task graph in the order in which they
+/// would be executed. Tasks that are not executable (e.g. aliases) are left
+/// out.
+fn planned_tasks<'p>(task_graph: &TaskGraph<'p>, reverse: bool) -> Vec<ExecutableTask<'p>> {
+    execution_order(task_graph, reverse)
+        .into_iter()
+        .map(|task_id| ExecutableTask::from_task_graph(task_graph, task_id))
+        .filter(|task| task.task().is_executable())
+        .collect()
+}
+
+/// Returns the line that `--print-inputs-hash` prints for a task: its name and
+/// the hash that its cache is compared with.
+async fn inputs_hash_line(
+    task: &ExecutableTask<'_>,
+    lock_file: &LockFile,
+) -> miette::Result<String> {
+    let name = task.name().unwrap_or("unnamed");
+    Ok(
+        match TaskHash::from_task(task, lock_file)
+            .await
+            .into_diagnostic()?
+        {
+            Some(hash) => format!("{name}: {}", hash.computation_hash()),
+            None => format!("{name}: not cached, the task has no inputs or outputs"),
+        },
+    )
+}
+
+/// Prints the execution plan of the task graph, including whether each task
+/// can currently be skipped because of a cache hit.
+async fn print_task_plan(
+    task_graph: &TaskGraph<'_>,
+    lock_file: &LockFile,
+    options: &ExecuteTaskOptions<'_>,
+    reverse: bool,
+) -> miette::Result<()> {
+    eprintln!(
+        "{}{}",
+        pixi_emoji("📋 ", ""),
+        console::style("Execution plan:").bold()
+    );
+    for (idx, executable_task) in planned_tasks(task_graph, reverse).into_iter().enumerate() {
+        let executable_task = executable_task
+            .with_task_cache_dir(options.task_cache_dir.clone())
+            .with_task_precedence(options.task_precedence);
+        let cache_status = if reverse {
+            String::new()
+        } else {
+            match executable_task
+                .can_skip(lock_file)
+                .await
+                .into_diagnostic()?
+            {
+                CanSkip::Yes(_) => console::style(" (cache hit)").green().to_string(),
+                CanSkip::No(_) => String::new(),
+            }
+        };
+        eprintln!(
+            "{:>4}. {} in {}: {}{}",
+            idx + 1,
+            console::style(executable_task.name().unwrap_or("unnamed"))
+                .green()
+                .bold(),
+            executable_task.run_environment.name().fancy_display(),
+            executable_task.display_command(),
+            cache_status
+        );
+    }
+    Ok(())
+}
+
+/// Prints the environment variables that are added or changed by the
+/// activation of `environment` compared to the current shell. When running
+/// with a clean environment, the variables that the task will not see are
+/// printed as well.
+fn print_env_diff(
+    environment: &Environment<'_>,
+    command_env: &HashMap<String, String>,
+    clean_env: bool,
+) {
+    eprintln!(
+        "{}{}",
+        pixi_emoji("🔍 ", ""),
+        console::style(format!(
+            "Environment of {} compared to the current shell:",
+            environment.name().fancy_display()
+        ))
+        .bold()
+    );
+
+    let current_env: HashMap<String, String> = std::env::vars().collect();
+    for (key, value) in command_env.iter().sorted_by_key(|(key, _)| *key) {
+        if current_env.get(key) != Some(value) {
+            println!("{}", console::style(format!("+{key}={value}")).green());
+        }
+    }
+
+    if clean_env {
+        for key in current_env
+            .keys()
+            .filter(|key| !command_env.contains_key(*key))
+            .sorted()
+        {
+            println!("{}", console::style(format!("-{key}")).red());
+        }
+    }
+}
+
+/// The differences between the conda packages installed in a prefix and the
+/// packages in the lock file, see `pixi run --env-check`. Packages are
+/// described as `name=version=build`.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct EnvironmentDrift {
+    missing: Vec<String>,
+    extra: Vec<String>,
+    /// The name, locked version and installed version of the package.
+    mismatched: Vec<(String, String, String)>,
+}
+
+impl EnvironmentDrift {
+    /// Compares the locked and the installed packages, both map the name of a
+    /// package to its `version=build`.
+    fn new(locked: &BTreeMap<String, String>, installed: &BTreeMap<String, String>) -> Self {
+        let mut drift = Self::default();
+        for (name, version) in locked {
+            match installed.get(name) {
+                None => drift.missing.push(format!("{name}={version}")),
+                Some(installed) if installed != version => {
+                    drift
+                        .mismatched
+                        .push((name.clone(), version.clone(), installed.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        for (name, version) in installed {
+            if !locked.contains_key(name) {
+                drift.extra.push(format!("{name}={version}"));
+            }
+        }
+        drift
+    }
+
+    fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+impl std::fmt::Display for EnvironmentDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for package in &self.missing {
+            writeln!(f, "  missing: {package}")?;
+        }
+        for package in &self.extra {
+            writeln!(f, "  extra: {package}")?;
+        }
+        for (name, locked, installed) in &self.mismatched {
+            writeln!(f, "  changed: {name} {locked} -> {installed}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the conda packages installed in the prefix of the environment with
+/// the packages in the lock file, see `pixi run --env-check`.
+fn check_environment_drift(
+    environment: &Environment<'_>,
+    lock_file: &LockFile,
+    prefix: &Prefix,
+    warn_only: bool,
+) -> miette::Result<()> {
+    let describe = |record: &PackageRecord| {
+        (
+            record.name.as_normalized().to_string(),
+            format!("{}={}", record.version, record.build),
+        )
+    };
+    let locked: BTreeMap<_, _> = lock_file
+        .environment(environment.name().as_str())
+        .map(|env| env.conda_repodata_records(environment.best_platform()))
+        .transpose()
+        .into_diagnostic()?
+        .flatten()
+        .unwrap_or_default()
+        .iter()
+        .map(|record| describe(&record.package_record))
+        .collect();
+    let installed: BTreeMap<_, _> = prefix
+        .find_installed_packages()?
+        .iter()
+        .map(|record| describe(&record.repodata_record.package_record))
+        .collect();
+
+    let drift = EnvironmentDrift::new(&locked, &installed);
+    if drift.is_empty() {
+        return Ok(());
+    }
+    let message = format!(
+        "the packages installed in the environment '{}' differ from the lock file:\n{}",
+        environment.name(),
+        drift.to_string().trim_end()
+    );
+    if warn_only {
+        tracing::warn!("{message}");
+        return Ok(());
+    }
+    Err(miette::miette!(
+        help = "run `pixi reinstall` to restore the environment from the lock file",
+        "{message}"
+    ))
+}
+
+/// Called when a command was not found.
+fn command_not_found<'p>(workspace: &'p Workspace, explicit_environment: Option<Environment<'p>>) {
+    let available_tasks = available_tasks(workspace, explicit_environment);
+
+    // Only annotate the tasks with their environments if there is more than one
+    // environment to choose from.
+    let annotate = available_tasks.values().flatten().unique().count() > 1;
+
+    if !available_tasks.is_empty() {
+        eprintln!(
+            "\nAvailable tasks:\n{}",
+            available_tasks
+                .iter()
+                .format_with("\n", |(name, environments), f| {
+                    if annotate {
+                        f(&format_args!(
+                            "\t{} ({})",
+                            name.fancy_display().bold(),
+                            environments
+                                .iter()
+                                .map(|env| env.fancy_display())
+                                .format(", ")
+                        ))
+                    } else {
+                        f(&format_args!("\t{}", name.fancy_display().bold()))
+                    }
+                })
+        );
+    }
+}
+
+/// Returns the tasks that are available in the given environment, or in all
+/// environments of the workspace if no environment was specified, together
+/// with the environments that provide them. Both the tasks and the
+/// environments are sorted by name.
+fn available_tasks<'p>(
+    workspace: &'p Workspace,
+    explicit_environment: Option<Environment<'p>>,
+) -> BTreeMap<TaskName, Vec<EnvironmentName>> {
+    let environments =
+        explicit_environment.map_or_else(|| workspace.environments(), |env| vec![env]);
+
+    let mut available_tasks: BTreeMap<TaskName, Vec<EnvironmentName>> = BTreeMap::new();
+    for environment in environments
+        .iter()
+        .sorted_by(|a, b| a.name().as_str().cmp(b.name().as_str()))
+    {
+        for task in environment.get_filtered_tasks() {
+            available_tasks
+                .entry(task)
+                .or_default()
+                .push(environment.name().clone());
+        }
+    }
+    available_tasks
+}
+
+#[derive(Debug, Error, Diagnostic)]
+enum TaskExecutionError {
+    #[error("the script exited with a non-zero exit code {0}")]
+    NonZeroExitCode(i32),
+
+    #[error(transparent)]
+    FailedToParseShellScript(#[from] FailedToParseShellScript),
+
+    #[error(transparent)]
+    EnvMergeConflict(#[from] EnvMergeConflict),
+
+    #[error(transparent)]
+    InvalidWorkingDirectory(#[from] InvalidWorkingDirectory),
+
+    #[error(transparent)]
+    UnsupportedPlatformError(#[from] UnsupportedPlatformError),
+
+    #[error("failed to open '{}' as the stdin of the task", .0.display())]
+    OpenStdin(PathBuf, #[source] std::io::Error),
+
+    #[error("failed to create a temporary directory for the task")]
+    CreateTempDir(#[source] std::io::Error),
+
+    #[error("Expected output matching '{pattern}', got: {output}")]
+    UnexpectedOutput { pattern: String, output: String },
+}
+
+/// Summarizes the number of packages that were added, removed and updated in
+/// the lock-file, or `None` if the lock-file didn't change.
+fn lock_file_changes_summary(diff: &LockFileDiff) -> Option<String> {
+    if diff.is_empty() {
+        return None;
+    }
+
+    // A package that changed on multiple platforms is only counted once.
+    let count = |select: fn(&PackagesDiff) -> Vec<&str>| {
+        diff.environment
+            .values()
+            .flat_map(|platforms| platforms.values())
+            .flat_map(select)
+            .unique()
+            .count()
+    };
+    let added = count(|packages| packages.added.iter().map(|p| p.name()).collect());
+    let removed = count(|packages| packages.removed.iter().map(|p| p.name()).collect());
+    let updated = count(|packages| packages.changed.iter().map(|(p, _)| p.name()).collect());
+
+    Some(format!(
+        "The lock-file was updated: {added} added, {removed} removed, {updated} updated"
+    ))
+}
+
+/// Resolves the directory passed to `--run-in` against the current directory
+/// and checks that it is an existing directory.
+fn run_in_directory(path: &Path) -> miette::Result<PathBuf> {
+    let path = std::env::current_dir().into_diagnostic()?.join(path);
+    if !path.is_dir() {
+        miette::bail!(
+            "the directory to run the task in '{}' does not exist or is not a directory",
+            path.display()
+        );
+    }
+    dunce::canonicalize(&path).into_diagnostic()
+}
+
+/// Set by `pixi run --no-emoji`, the output is printed from several tasks of
+/// the runtime so a thread-local wouldn't do.
+static NO_EMOJI: AtomicBool = AtomicBool::new(false);
+
+/// Returns an emoji that is printed as the fallback if the terminal doesn't
+/// support emoji or `--no-emoji` was passed.
+fn pixi_emoji<'a>(emoji: &'a str, fallback: &'a str) -> console::Emoji<'a, 'a> {
+    pixi_emoji_with(NO_EMOJI.load(Ordering::Relaxed), emoji, fallback)
+}
+
+/// Returns the emoji, or always the fallback if `no_emoji` is set.
+fn pixi_emoji_with<'a>(
+    no_emoji: bool,
+    emoji: &'a str,
+    fallback: &'a str,
+) -> console::Emoji<'a, 'a> {
+    if no_emoji {
+        console::Emoji(fallback, fallback)
+    } else {
+        console::Emoji(emoji, fallback)
+    }
+}
+
+/// Returns the directory to store the task cache of the workspace in when
+/// `--task-cache-dir` is given, and makes sure it exists and is writable. The
+/// directory is named after a hash of the workspace root to prevent collisions
+/// between workspaces.
+fn task_cache_dir(workspace: &Workspace, dir: &Path) -> miette::Result<PathBuf> {
+    let mut hasher = Xxh3::new();
+    workspace.root().hash(&mut hasher);
+    let dir = std::env::current_dir()
+        .into_diagnostic()?
+        .join(dir)
+        .join(format!("{:x}", hasher.finish()));
+    fs_err::create_dir_all(&dir).into_diagnostic()?;
+    tempfile::tempfile_in(&dir)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            format!(
+                "the task cache directory '{}' is not writable",
+                dir.display()
+            )
+        })?;
+    Ok(dir)
+}
+
+/// The environment variable that holds the file a task runs for with `pixi run
+/// --each`.
+const EACH_FILE_ENV_VAR: &str = "PIXI_EACH_FILE";
+
+/// Returns the files that match the `--each` globs in the workspace root,
+/// sorted and without duplicates.
+fn each_files(workspace: &Workspace, patterns: &[String]) -> miette::Result<Vec<PathBuf>> {
+    let mut files = BTreeSet::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("invalid glob '{pattern}' passed to `--each`"))?;
+        for entry in glob.walk(workspace.root()) {
+            let entry = entry.into_diagnostic()?;
+            if entry.file_type().is_file() {
+                files.insert(entry.into_path());
+            }
+        }
+    }
+    if files.is_empty() {
+        miette::bail!(
+            "no files match {}",
+            patterns
+                .iter()
+                .map(|pattern| format!("'{pattern}'"))
+                .join(", ")
+        );
+    }
+    Ok(files.into_iter().collect())
+}
+
+/// The environment variable that holds the path of the temporary directory of
+/// a task.
+const TEMPDIR_ENV_VAR: &str = "PIXI_TASK_TEMPDIR";
+
+/// Options that control how the command of a task is executed.
+#[derive(Default)]
+struct ExecuteTaskOptions<'a> {
+    /// The directory to run the command in instead of the working directory
+    /// of the task.
+    run_in: Option<&'a Path>,
+
+    /// Where the stdin of the command is read from.
+    stdin: TaskStdin,
+
+    /// The file that the output of the command is also written to.
+    log_file: Option<LogFile>,
+
+    /// The directory to store the task cache in instead of the `.pixi`
+    /// directory of the workspace.
+    task_cache_dir: Option<PathBuf>,
+
+    /// Where the additional arguments end up in the command of the tasks.
+    task_precedence: TaskPrecedence,
+
+    /// The command that the stdout of the command is piped through.
+    pipe_through: Option<SequentialList>,
+
+    /// Whether to create a temporary directory for every command.
+    tempdir: bool,
+
+    /// The command that runs after all tasks succeeded.
+    on_success: Option<SequentialList>,
+
+    /// The command that runs after a task failed.
+    on_failure: Option<SequentialList>,
+
+    /// Reports the results of the tasks.
+    report: TaskReport,
+
+    /// The files that the requested task runs for, once per file.
+    each_files: Vec<PathBuf>,
+
+    /// The number of files that are processed at the same time.
+    each_jobs: usize,
+
+    /// The patterns that the stdout of every task has to match.
+    assert_output: Vec<Regex>,
+
+    /// The task of `--task-url`, which runs instead of a task of the
+    /// workspace.
+    remote_task: Option<Custom>,
+}
+
+/// Verifies that the output of a task matches all the patterns passed to
+/// `--assert-output`.
+fn check_output(patterns: &[Regex], output: &[u8]) -> Result<(), TaskExecutionError> {
+    let output = String::from_utf8_lossy(output);
+    match patterns.iter().find(|pattern| !pattern.is_match(&output)) {
+        Some(pattern) => Err(TaskExecutionError::UnexpectedOutput {
+            pattern: pattern.to_string(),
+            output: output.chars().take(100).collect(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Called to execute a single command.
+///
+/// This function is called from [`execute`].
+async fn execute_task(
+    task: &ExecutableTask<'_>,
+    command_env: &HashMap<String, String>,
+    options: &ExecuteTaskOptions<'_>,
+) -> Result<(), TaskExecutionError> {
+    task.check_env_merge(command_env)?;
+    let Some(script) = task.as_deno_script(command_env)? else {
+        return Ok(());
+    };
+
+    // The temporary directory is removed when it is dropped at the end of this
+    // function, regardless of whether the task succeeded.
+    let mut command_env = Cow::Borrowed(command_env);
+    let _tempdir = if options.tempdir || task.task().tempdir() {
+        let tempdir = tempfile::Builder::new()
+            .prefix("pixi-task-")
+            .tempdir()
+            .map_err(TaskExecutionError::CreateTempDir)?;
+        command_env.to_mut().insert(
+            TEMPDIR_ENV_VAR.to_string(),
+            tempdir.path().display().to_string(),
+        );
+        Some(tempdir)
+    } else {
+        None
+    };
+    let cwd = match options.run_in {
+        Some(run_in) => run_in.to_path_buf(),
+        None => task.working_directory()?,
+    };
+
+    let (stdout, stdout_handle) = if options.report.format == OutputFormat::Tap {
+        tap_output_writer(options.log_file.as_ref())
+    } else {
+        output_writer(
+            std::io::stdout(),
+            ShellPipeWriter::stdout(),
+            options.log_file.as_ref(),
+        )
+    };
+    let (stderr, stderr_handle) = output_writer(
+        std::io::stderr(),
+        ShellPipeWriter::stderr(),
+        options.log_file.as_ref(),
+    );
+    let state = ShellState::new(
+        (*command_env).clone(),
+        &cwd,
+        Default::default(),
+        Default::default(),
+    );
+    let (stdout, captured_stdout) = if options.assert_output.is_empty() {
+        (stdout, None)
+    } else {
+        let (stdout, captured) = capturing_writer(stdout);
+        (stdout, Some(captured))
+    };
+    let stdin = options.stdin.reader()?;
+    let mut handles = vec![stdout_handle, stderr_handle];
+    let status_code = match &options.pipe_through {
+        None => execute_with_pipes(script, state, stdin, stdout, stderr).await,
+        Some(filter) => {
+            // Connect the stdout of the task to the stdin of the filter, the
+            // exit code of the filter doesn't affect the result of the task.
+            let (filter_stdin, task_stdout) = pipe();
+            let (filter_stderr, filter_stderr_handle) = output_writer(
+                std::io::stderr(),
+                ShellPipeWriter::stderr(),
+                options.log_file.as_ref(),
+            );
+            handles.push(filter_stderr_handle);
+            let filter_state = ShellState::new(
+                (*command_env).clone(),
+                &cwd,
+                Default::default(),
+                Default::default(),
+            );
+            let (status_code, filter_status_code) = tokio::join!(
+                execute_with_pipes(script, state, stdin, task_stdout, stderr),
+                execute_with_pipes(
+                    filter.clone(),
+                    filter_state,
+                    filter_stdin,
+                    stdout,
+                    filter_stderr
+                ),
+            );
+            if filter_status_code != 0 {
+                tracing::warn!(
+                    "the command to pipe the output through exited with a non-zero exit code {filter_status_code}"
+                );
+            }
+            status_code
+        }
+    };
+
+    // Wait until all the output is written to the log file.
+    let captured_stdout = match captured_stdout {
+        Some(handle) => Some(handle.await.expect("should be able to capture the output")),
+        None => None,
+    };
+    for handle in handles.into_iter().flatten() {
+        handle.await.expect("should be able to write the output");
+    }
+
+    if status_code != 0 {
+        return Err(TaskExecutionError::NonZeroExitCode(status_code));
+    }
+
+    if let Some(captured_stdout) = captured_stdout {
+        check_output(&options.assert_output, &captured_stdout)?;
+    }
+
+    Ok(())
+}
+
+/// Runs the task once for every file of `--each`, with the path of the file in
+/// `PIXI_EACH_FILE`. All files are processed even if the task fails for some of
+/// them, the error of the first failed file is returned.
+async fn execute_task_each(
+    task: &ExecutableTask<'_>,
+    command_env: &HashMap<String, String>,
+    options: &ExecuteTaskOptions<'_>,
+) -> Result<(), TaskExecutionError> {
+    let results: Vec<_> = futures::stream::iter(&options.each_files)
+        .map(|file| async move {
+            let mut command_env = command_env.clone();
+            command_env.insert(EACH_FILE_ENV_VAR.to_string(), file.display().to_string());
+            (file, execute_task(task, &command_env, options).await)
+        })
+        .buffered(options.each_jobs.max(1))
+        .collect()
+        .await;
+
+    let failed = results
+        .iter()
+        .filter_map(|(file, result)| result.is_err().then_some(file))
+        .collect_vec();
+    if !failed.is_empty() {
+        eprintln!(
+            "{}the task '{}' failed for {} of {} files:",
+            console::style(pixi_emoji("✗ ", "")).red(),
+            task.name().unwrap_or("unnamed"),
+            failed.len(),
+            results.len(),
+        );
+        for file in failed {
+            eprintln!("  {}", file.display());
+        }
+    }
+    results
+        .into_iter()
+        .map(|(_, result)| result)
+        .find(Result::is_err)
+        .unwrap_or(Ok(()))
+}
+
+/// Called to disambiguate between environments to run a task in.
+fn disambiguate_task_interactive<'p>(
+    problem: &AmbiguousTask<'p>,
+) -> Option<TaskAndEnvironment<'p>> {
+    let environment_names = problem
+        .environments
+        .iter()
+        .map(|(env, _)| env.name())
+        .collect_vec();
+    let theme = ColorfulTheme {
+        active_item_style: console::Style::new().for_stderr().magenta(),
+        ..ColorfulTheme::default()
+    };
+
+    dialoguer::Select::with_theme(&theme)
+        .with_prompt(format!(
+            "The task '{}' {}can be run in multiple environments.\n\nPlease select an environment to run the task in:",
+            problem.task_name.fancy_display(),
+            if let Some(dependency) = &problem.depended_on_by {
+                format!("(depended on by '{}') ", dependency.0.fancy_display())
+            } else {
+                String::new()
+            }
+        ))
+        .report(false)
+        .items(&environment_names)
+        .default(0)
+        .interact_opt()
+        .map_or(None, identity)
+        .map(|idx| problem.environments[idx].clone())
+}
+
+/// `dialoguer` doesn't clean up your term if it's aborted via e.g. `SIGINT` or
+/// other exceptions: https://github.com/console-rs/dialoguer/issues/188.
+///
+/// `dialoguer`, as a library, doesn't want to mess with signal handlers,
+/// but we, as an application, are free to mess with signal handlers if we feel
+/// like it, since we own the process.
+/// This function was taken from https://github.com/dnjstrom/git-select-branch/blob/16c454624354040bc32d7943b9cb2e715a5dab92/src/main.rs#L119
+fn reset_cursor() {
+    let term = console::Term::stdout();
+    let _ = term.show_cursor();
+}
+
+/// Whether CTRL-C exits pixi. It is turned off while a task runs, so that the
+/// task can handle the signal itself.
+static CTRLC_SHOULD_EXIT_PROCESS: AtomicBool = AtomicBool::new(true);
+
+/// Installs the CTRL-C handler of `pixi run`.
+///
+/// A process can only have a single handler, so running tasks more than once
+/// in the same process reuses the handler that was installed first.
+fn install_ctrlc_handler() -> miette::Result<()> {
+    reuse_ctrlc_handler(ctrlc::set_handler(|| {
+        reset_cursor();
+        if CTRLC_SHOULD_EXIT_PROCESS.load(Ordering::Relaxed) {
+            exit_process_on_sigint();
+        }
+    }))
+}
+
+/// Treats an already installed CTRL-C handler as success, see
+/// [`install_ctrlc_handler`].
+fn reuse_ctrlc_handler(result: Result<(), ctrlc::Error>) -> miette::Result<()> {
+    match result {
+        Ok(()) | Err(ctrlc::Error::MultipleHandlers) => Ok(()),
+        Err(err) => Err(err).into_diagnostic(),
+    }
+}
+
+/// Exit the process with the appropriate exit code for a SIGINT.
+fn exit_process_on_sigint() {
+    // https://learn.microsoft.com/en-us/cpp/c-runtime-library/signal-constants
+    #[cfg(target_os = "windows")]
+    std::process::exit(3);
+
+    // POSIX compliant OSs: 128 + SIGINT (2)
+    #[cfg(not(target_os = "windows"))]
+    std::process::exit(130);
+}
+
+#[cfg(test)]
+mod tests {
+    use rattler_conda_types::Platform;
+
+    use super::*;
+
+    #[test]
+    fn test_plan_lists_tasks_in_dependency_order() {
+        let workspace = harness::workspace(
+            Path::new("pixi.toml"),
+            r#"
+        [tasks]
+        root = "echo root"
+        task1 = {cmd="echo task1", depends-on=["root"]}
+        task2 = {cmd="echo task2", depends-on=["root"]}
+        top = {cmd="echo top", depends-on=["task1","task2"]}
+        all = {depends-on=["top"]}
+    "#,
+        );
+        let task_graph = harness::task_graph(&workspace, None, "all");
+
+        let plan = planned_tasks(&task_graph, false)
+            .iter()
+            .map(|task| task.name().unwrap().to_string())
+            .collect_vec();
+        assert_eq!(plan, vec!["root", "task1", "task2", "top"]);
+
+        let plan = planned_tasks(&task_graph, true)
+            .iter()
+            .map(|task| task.name().unwrap().to_string())
+            .collect_vec();
+        assert_eq!(plan, vec!["top", "task2", "task1", "root"]);
+    }
+
+    #[test]
+    fn test_reverse_order_of_chain() {
+        let workspace = harness::workspace(
+            Path::new("pixi.toml"),
+            r#"
+        [tasks]
+        stop-db = "echo stop-db"
+        stop-cache = {cmd="echo stop-cache", depends-on=["stop-db"]}
+        stop-app = {cmd="echo stop-app", depends-on=["stop-cache"]}
+    "#,
+        );
+        let task_graph = harness::task_graph(&workspace, None, "stop-app");
+
+        let order = execution_order(&task_graph, true)
+            .into_iter()
+            .map(|id| ExecutableTask::from_task_graph(&task_graph, id))
+            .map(|task| task.name().unwrap().to_string())
+            .collect_vec();
+        assert_eq!(order, vec!["stop-app", "stop-cache", "stop-db"]);
+    }
+
+    #[test]
+    fn test_environment_glob_selects_matching_environments() {
+        let workspace = harness::workspace(
+            Path::new("pixi.toml"),
+            r#"
+        [feature.py39.dependencies]
+        python = "3.9.*"
+        [feature.py310.dependencies]
+        python = "3.10.*"
+        [feature.py311.dependencies]
+        python = "3.11.*"
+        [environments]
+        py39 = ["py39"]
+        py310 = ["py310"]
+        py311 = ["py311"]
+    "#,
+        );
+
+        let names = |pattern: &str| {
+            explicit_environments(&workspace, Some(pattern.to_string()))
+                .unwrap()
+                .into_iter()
+                .map(|env| env.unwrap().name().to_string())
+                .collect_vec()
+        };
+        assert_eq!(names("py31*"), vec!["py310", "py311"]);
+        assert_eq!(names("py3*").len(), 3);
+        assert_eq!(names("py39"), vec!["py39"]);
+
+        assert!(explicit_environments(&workspace, Some("py2*".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_environment_all_skips_unavailable_environments() {
+        // An environment that only supports another platform is skipped.
+        let other_platform = if Platform::current() == Platform::Win64 {
+            "linux-64"
+        } else {
+            "win-64"
+        };
+        let workspace = harness::workspace(
+            Path::new("pixi.toml"),
+            &format!(
+                r#"
+        [feature.a.tasks]
+        test = "echo a"
+        [feature.b.tasks]
+        test = "echo b"
+        [feature.c.tasks]
+        other = "echo c"
+        [feature.d]
+        platforms = ["{other_platform}"]
+        [feature.d.tasks]
+        test = "echo d"
+        [environments]
+        a = ["a"]
+        b = ["b"]
+        c = ["c"]
+        d = ["d"]
+    "#
+            ),
+        );
+
+        let names = |task: &str| {
+            let (environments, skipped) = environments_for_task(&workspace, Some(task));
+            (
+                environments
+                    .iter()
+                    .map(|env| env.name().to_string())
+                    .collect_vec(),
+                skipped
+                    .iter()
+                    .map(|(env, _)| env.name().to_string())
+                    .collect_vec(),
+            )
+        };
+        assert_eq!(names("test"), (vec!["a", "b"], vec!["c", "d", "default"]));
+        // A shell command runs in every environment of the platform.
+        assert_eq!(
+            names("echo hello"),
+            (vec!["a", "b", "c", "default"], vec!["d"])
+        );
+        // So does a custom command, even if a task has the same name.
+        let (environments, _) = environments_for_task(&workspace, None);
+        assert_eq!(environments.len(), 4);
+
+        // Every environment that runs the task gets its own banner.
+        let banners = environments_for_task(&workspace, Some("test"))
+            .0
+            .iter()
+            .map(|env| console::strip_ansi_codes(&environment_banner(env.name())).into_owned())
+            .collect_vec();
+        assert_eq!(banners, vec!["Environment a", "Environment b"]);
+    }
+
+    #[test]
+    fn test_environment_name_from_dir() {
+        let workspace = harness::workspace(
+            Path::new("/workspace/pixi.toml"),
+            r#"
+        [feature.frontend.tasks]
+        start = "echo frontend"
+        [feature.backend.tasks]
+        start = "echo backend"
+        [environments]
+        frontend = ["frontend"]
+        backend = ["backend"]
+    "#,
+        );
+        let root = workspace.root().to_path_buf();
+
+        let name = |dir: PathBuf| environment_name_from_dir(&workspace, &dir);
+        assert_eq!(name(root.join("backend")).as_deref(), Some("backend"));
+        assert_eq!(
+            name(root.join("backend/src/api")).as_deref(),
+            Some("backend")
+        );
+        assert_eq!(
+            name(root.join("frontend/backend")).as_deref(),
+            Some("backend")
+        );
+        assert_eq!(name(root.join("docs")), None);
+        assert_eq!(name(root.clone()), None);
+        assert_eq!(name(PathBuf::from("/elsewhere/backend")), None);
+    }
+
+    #[test]
+    fn test_available_tasks_by_environment() {
+        let workspace = harness::workspace(
+            Path::new("pixi.toml"),
+            r#"
+        [tasks]
+        common = "echo common"
+        [feature.test.tasks]
+        only-test = "echo test"
+        [feature.lint.tasks]
+        only-lint = "echo lint"
+        _hidden = "echo hidden"
+        [environments]
+        test = ["test"]
+        lint = ["lint"]
+    "#,
+        );
+
+        let tasks = |environment: Option<Environment>| {
+            available_tasks(&workspace, environment)
+                .into_iter()
+                .map(|(name, envs)| format!("{name}: {}", envs.iter().format(", ")))
+                .collect_vec()
+        };
+
+        assert_eq!(
+            tasks(None),
+            vec![
+                "common: default, lint, test",
+                "only-lint: lint",
+                "only-test: test"
+            ]
+        );
+        assert_eq!(
+            tasks(workspace.environment("test")),
+            vec!["common: test", "only-test: test"]
+        );
+    }
+
+    #[test]
+    fn test_default_task() {
+        let workspace_with_tasks =
+            |tasks: &str| harness::workspace(Path::new("pixi.toml"), &format!("[tasks]\n{tasks}"));
+
+        let workspace = workspace_with_tasks(
+            r#"default = { depends-on = ["build"] }
+        build = "echo build""#,
+        );
+        assert!(has_default_task(&workspace, &[None]));
+        let task_graph = harness::task_graph(&workspace, None, DEFAULT_TASK);
+        let plan = planned_tasks(&task_graph, false)
+            .iter()
+            .map(|task| task.name().unwrap().to_string())
+            .collect_vec();
+        assert_eq!(plan, vec!["build"]);
+
+        let workspace = workspace_with_tasks(r#"build = "echo build""#);
+        assert!(!has_default_task(&workspace, &[None]));
+    }
+
+    #[test]
+    fn test_task_cache_dir_per_workspace() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workspace =
+            |root: &str| harness::workspace(&tmp.path().join(root).join("pixi.toml"), "");
+        let cache_dir = tmp.path().join("cache");
+
+        let first = task_cache_dir(&workspace("first"), &cache_dir).unwrap();
+        let second = task_cache_dir(&workspace("second"), &cache_dir).unwrap();
+        assert!(first.is_dir());
+        assert!(first.starts_with(&cache_dir));
+        assert_ne!(first, second);
+        assert_eq!(
+            first,
+            task_cache_dir(&workspace("first"), &cache_dir).unwrap()
+        );
+
+        // A cache directory that can't be created is an error
+        let file = tmp.path().join("file");
+        fs_err::write(&file, "").unwrap();
+        assert!(task_cache_dir(&workspace("first"), &file).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_in_differs_from_workspace_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        let subproject = tmp.path().join("subproject");
+        fs_err::create_dir(&subproject).unwrap();
+
+        let workspace = harness::workspace(
+            &tmp.path().join("pixi.toml"),
+            r#"
+        [tasks]
+        hello = "echo hello > hello.txt"
+    "#,
+        );
+        let task = harness::planned_task(&workspace, "hello");
+
+        let run_in = run_in_directory(&subproject).unwrap();
+        let options = ExecuteTaskOptions {
+            run_in: Some(&run_in),
+            stdin: TaskStdin::Passthrough(StdinPassthrough::Null),
+            ..Default::default()
+        };
+        execute_task(&task, &HashMap::new(), &options)
+            .await
+            .unwrap();
+
+        assert!(subproject.join("hello.txt").is_file());
+        assert!(!workspace.root().join("hello.txt").exists());
+
+        assert!(run_in_directory(&tmp.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_lock_file_changes_summary() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/lockfiles/archspec.lock");
+        let lock_file = LockFile::from_path(&path).unwrap();
+
+        // Nothing is printed when the lock-file didn't change
+        let diff = LockFileDiff::from_lock_files(&lock_file, &lock_file);
+        assert_eq!(lock_file_changes_summary(&diff), None);
+
+        // All packages are reported as added to an empty lock-file
+        let diff = LockFileDiff::from_lock_files(&LockFile::default(), &lock_file);
+        let summary = lock_file_changes_summary(&diff).unwrap();
+        assert!(
+            summary.ends_with("6 added, 0 removed, 0 updated"),
+            "{summary}"
+        );
+
+        let diff = LockFileDiff::from_lock_files(&lock_file, &LockFile::default());
+        let summary = lock_file_changes_summary(&diff).unwrap();
+        assert!(
+            summary.ends_with("0 added, 6 removed, 0 updated"),
+            "{summary}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pipe_through_keeps_exit_code_of_task() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workspace = harness::workspace(
+            &tmp.path().join("pixi.toml"),
+            r#"
+        [tasks]
+        hello = "echo hello"
+        fail = "echo failing && exit 3"
+    "#,
+        );
+        let task = |name: &str| harness::planned_task(&workspace, name);
+
+        let log_path = tmp.path().join("task.log");
+        let options = |filter: &str| ExecuteTaskOptions {
+            stdin: TaskStdin::Passthrough(StdinPassthrough::Null),
+            log_file: Some(open_log_file(&log_path, true).unwrap()),
+            pipe_through: Some(deno_task_shell::parser::parse(filter).unwrap()),
+            ..Default::default()
+        };
+
+        // The output of the task is passed through the filter
+        execute_task(
+            &task("hello"),
+            &HashMap::new(),
+            &options("cat && echo filtered"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            fs_err::read_to_string(&log_path).unwrap(),
+            "hello\nfiltered\n"
+        );
+
+        // A failing filter doesn't fail the task
+        execute_task(&task("hello"), &HashMap::new(), &options("exit 1"))
+            .await
+            .unwrap();
+
+        // The exit code of the task is kept, even if the filter succeeds
+        let err = execute_task(&task("fail"), &HashMap::new(), &options("cat"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TaskExecutionError::NonZeroExitCode(3)));
+    }
+
+    #[test]
+    fn test_unsupported_platform_error() {
+        let workspace = Workspace::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64", "linux-riscv64", "linux-ppc64le"]
+        [feature.riscv]
+        platforms = ["linux-riscv64", "linux-ppc64le"]
+        [feature.riscv.tasks]
+        build = "echo build"
+        [environments]
+        riscv = ["riscv"]
+    "#,
+        )
+        .unwrap();
+        let task = harness::planned_task(&workspace, "build");
+
+        let err = check_platform_support(&task).unwrap_err();
+        assert_eq!(err.task.as_ref().map(TaskName::as_str), Some("build"));
+        assert_eq!(err.platform, task.run_environment.best_platform());
+        let message = err.to_string();
+        assert!(message.contains("build"));
+        assert!(message.contains(err.platform.as_str()));
+        let help = err.help().unwrap().to_string();
+        assert!(help.contains("linux-ppc64le, linux-riscv64"));
+    }
+
+    #[tokio::test]
+    async fn test_task_tempdir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workspace = harness::workspace(
+            &tmp.path().join("pixi.toml"),
+            r#"
+        [tasks]
+        scratch = { cmd = "mkdir $PIXI_TASK_TEMPDIR/scratch && echo $PIXI_TASK_TEMPDIR", tempdir = true }
+        fail = { cmd = "echo $PIXI_TASK_TEMPDIR && exit 1", tempdir = true }
+        plain = "echo $PIXI_TASK_TEMPDIR"
+    "#,
+        );
+        let task = |name: &str| harness::planned_task(&workspace, name);
+
+        let log_path = tmp.path().join("task.log");
+        let run = |name: &str, tempdir: bool| {
+            let task = task(name);
+            let log_path = log_path.clone();
+            async move {
+                fs_err::write(&log_path, "").unwrap();
+                let options = ExecuteTaskOptions {
+                    stdin: TaskStdin::Passthrough(StdinPassthrough::Null),
+                    log_file: Some(open_log_file(&log_path, true).unwrap()),
+                    tempdir,
+                    ..Default::default()
+                };
+                let result = execute_task(&task, &HashMap::new(), &options).await;
+                let output = fs_err::read_to_string(&log_path).unwrap();
+                (result.is_ok(), output.trim().to_string())
+            }
+        };
+
+        // The directory exists while the task runs and is removed afterwards
+        let (success, dir) = run("scratch", false).await;
+        assert!(success);
+        assert!(!dir.is_empty());
+        assert!(!Path::new(&dir).exists());
+
+        // It is also removed if the task fails
+        let (success, dir) = run("fail", false).await;
+        assert!(!success);
+        assert!(!dir.is_empty());
+        assert!(!Path::new(&dir).exists());
+
+        // Tasks only get a directory if they or `--tempdir` ask for one
+        assert_eq!(run("plain", false).await, (true, String::new()));
+        let (_, dir) = run("plain", true).await;
+        assert!(!dir.is_empty());
+    }
+
+    #[test]
+    fn test_task_completion_script() {
+        let tasks = vec!["build".to_string(), "test".to_string()];
+
+        let script = CompletionShell::Fish.task_completion_script("pixi", &tasks);
+        assert!(script.contains("function __pixi_run_completing"));
+        assert!(script.contains("printf '%s\\n' 'build' 'test'\n"));
+        assert!(script.contains(
+            "complete -c pixi -n '__pixi_run_completing' -f -a '(__pixi_run_completing_tasks)'\n"
+        ));
+
+        let script = CompletionShell::Bash.task_completion_script("pixi", &tasks);
+        assert!(script.contains("local task tasks=('build' 'test')"));
+        assert!(script.contains("complete -F __pixi_run_completing"));
+
+        let script = CompletionShell::Zsh.task_completion_script("pixi", &tasks);
+        assert!(script.contains("tasks=('build' 'test')"));
+
+        let script = CompletionShell::Powershell.task_completion_script("pixi", &tasks);
+        assert!(script.contains("@('build', 'test')"));
+    }
+
+    #[test]
+    fn test_task_completion_script_quotes_task_names() {
+        let tasks = vec!["it's a $task".to_string()];
+
+        let script = CompletionShell::Bash.task_completion_script("pixi", &tasks);
+        assert!(script.contains(r"tasks=('it'\''s a $task')"));
+
+        let script = CompletionShell::Zsh.task_completion_script("pixi", &tasks);
+        assert!(script.contains(r"tasks=('it'\''s a $task')"));
+
+        let script = CompletionShell::Fish.task_completion_script("pixi", &tasks);
+        assert!(script.contains(r"printf '%s\n' 'it\'s a $task'"));
+
+        let script = CompletionShell::Powershell.task_completion_script("pixi", &tasks);
+        assert!(script.contains("@('it''s a $task')"));
+    }
+
+    #[test]
+    fn test_task_graph_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut workspace = harness::workspace(
+            &tmp.path().join("pixi.toml"),
+            r#"
+        [tasks]
+        build = "echo build"
+        all = {depends-on=["test"]}
+    "#,
+        );
+        let tasks_file = tmp.path().join("tasks.toml");
+        fs_err::write(
+            &tasks_file,
+            r#"
+        [tasks]
+        test = {cmd="echo test", depends-on=["build"]}
+    "#,
+        )
+        .unwrap();
+        add_task_graph_files(&mut workspace, &[tasks_file.clone()]).unwrap();
+
+        // The tasks of the file and the workspace depend on each other
+        let task_graph = harness::task_graph(&workspace, None, "all");
+        let plan = planned_tasks(&task_graph, false)
+            .iter()
+            .map(|task| task.name().unwrap().to_string())
+            .collect_vec();
+        assert_eq!(plan, vec!["build", "test"]);
+
+        // Redefining a task is an error
+        assert!(add_task_graph_files(&mut workspace, &[tasks_file]).is_err());
+    }
+
+    #[test]
+    fn test_environment_drift() {
+        let packages = |packages: &[(&str, &str)]| {
+            packages
+                .iter()
+                .map(|(name, version)| (name.to_string(), version.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let locked = packages(&[
+            ("python", "3.12.0=h1"),
+            ("numpy", "2.0.0=h2"),
+            ("zlib", "1.3=h3"),
+        ]);
+
+        assert!(EnvironmentDrift::new(&locked, &locked).is_empty());
+
+        let installed = packages(&[
+            ("python", "3.12.0=h1"),
+            ("numpy", "2.1.0=h4"),
+            ("requests", "2.32.0=h5"),
+        ]);
+        let drift = EnvironmentDrift::new(&locked, &installed);
+        assert_eq!(
+            drift,
+            EnvironmentDrift {
+                missing: vec!["zlib=1.3=h3".to_string()],
+                extra: vec!["requests=2.32.0=h5".to_string()],
+                mismatched: vec![(
+                    "numpy".to_string(),
+                    "2.0.0=h2".to_string(),
+                    "2.1.0=h4".to_string()
+                )],
+            }
+        );
+        assert_eq!(
+            drift.to_string(),
+            "  missing: zlib=1.3=h3\n  extra: requests=2.32.0=h5\n  changed: numpy 2.0.0=h2 -> 2.1.0=h4\n"
+        );
+    }
+
+    #[test]
+    fn test_reuse_ctrlc_handler() {
+        reuse_ctrlc_handler(Ok(())).unwrap();
+        reuse_ctrlc_handler(Err(ctrlc::Error::MultipleHandlers)).unwrap();
+        let err = reuse_ctrlc_handler(Err(ctrlc::Error::System(std::io::Error::other(
+            "no signals",
+        ))));
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_no_emoji() {
+        assert_eq!(pixi_emoji_with(true, "✨ ", "* ").to_string(), "* ");
+        let emoji = pixi_emoji_with(false, "✨ ", "* ");
+        assert_eq!((emoji.0, emoji.1), ("✨ ", "* "));
+    }
+
+    #[tokio::test]
+    async fn test_each_runs_task_per_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let data = tmp.path().join("data");
+        fs_err::create_dir(&data).unwrap();
+        for name in ["a.csv", "b.csv", "c.csv", "ignored.txt"] {
+            fs_err::write(data.join(name), "").unwrap();
+        }
+
+        let workspace = harness::workspace(
+            &tmp.path().join("pixi.toml"),
+            r#"
+        [tasks]
+        process = "echo $PIXI_EACH_FILE >> processed.txt"
+    "#,
+        );
+        let files = each_files(&workspace, &["data/*.csv".to_string()]).unwrap();
+        assert_eq!(
+            files,
+            ["a.csv", "b.csv", "c.csv"].map(|name| data.join(name))
+        );
+        assert!(each_files(&workspace, &["missing/*.csv".to_string()]).is_err());
+
+        let task = harness::planned_task(&workspace, "process");
+        let options = ExecuteTaskOptions {
+            stdin: TaskStdin::Passthrough(StdinPassthrough::Null),
+            each_files: files.clone(),
+            each_jobs: 2,
+            ..Default::default()
+        };
+        execute_task_each(&task, &HashMap::new(), &options)
+            .await
+            .unwrap();
+
+        // The task ran once for every file
+        let processed = fs_err::read_to_string(tmp.path().join("processed.txt")).unwrap();
+        assert_eq!(
+            processed.lines().sorted().collect_vec(),
+            files
+                .iter()
+                .map(|file| file.display().to_string())
+                .collect_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inputs_hash_changes_with_inputs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workspace = harness::workspace(
+            &tmp.path().join("pixi.toml"),
+            r#"
+        [tasks]
+        build = {cmd = "echo build", inputs = ["src/*.txt"]}
+        uncached = "echo uncached"
+    "#,
+        );
+        fs_err::create_dir(tmp.path().join("src")).unwrap();
+        fs_err::write(tmp.path().join("src/input.txt"), "first").unwrap();
+
+        let task = |name: &str| harness::planned_task(&workspace, name);
+        let lock_file = LockFile::default();
+
+        let first = inputs_hash_line(&task("build"), &lock_file).await.unwrap();
+        assert!(first.starts_with("build: "));
+        assert_eq!(
+            first,
+            inputs_hash_line(&task("build"), &lock_file).await.unwrap()
+        );
+
+        // Changing an input changes the hash
+        fs_err::write(tmp.path().join("src/input.txt"), "second").unwrap();
+        assert_ne!(
+            first,
+            inputs_hash_line(&task("build"), &lock_file).await.unwrap()
+        );
+
+        assert_eq!(
+            inputs_hash_line(&task("uncached"), &lock_file)
+                .await
+                .unwrap(),
+            "uncached: not cached, the task has no inputs or outputs"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_assert_output() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workspace = harness::workspace(
+            &tmp.path().join("pixi.toml"),
+            r#"
+        [tasks]
+        test = "echo 3 passed in 0.12s"
+    "#,
+        );
+        let task = harness::planned_task(&workspace, "test");
+        let options = |patterns: &[&str]| ExecuteTaskOptions {
+            stdin: TaskStdin::Passthrough(StdinPassthrough::Null),
+            assert_output: patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern).unwrap())
+                .collect(),
+            ..Default::default()
+        };
+
+        // All patterns have to match
+        execute_task(
+            &task,
+            &HashMap::new(),
+            &options(&[r"\d+ passed", "in [0-9.]+s"]),
+        )
+        .await
+        .unwrap();
+        let err = execute_task(&task, &HashMap::new(), &options(&[r"\d+ passed", "failed"]))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected output matching 'failed', got: 3 passed in 0.12s\n"
+        );
+
+        // Only the start of the output is shown
+        let output = "x".repeat(200);
+        let TaskExecutionError::UnexpectedOutput { output, .. } =
+            check_output(&[Regex::new("y").unwrap()], output.as_bytes()).unwrap_err()
+        else {
+            panic!("expected an unexpected output error");
+        };
+        assert_eq!(output.len(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_task_stdin() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workspace = harness::workspace(
+            &tmp.path().join("pixi.toml"),
+            r#"
+        [tasks]
+        read-stdin = "cat > stdin.txt"
+    "#,
+        );
+        let task = harness::planned_task(&workspace, "read-stdin");
+        let input = tmp.path().join("input.txt");
+        fs_err::write(&input, "from a file\n").unwrap();
+        for (stdin, expected) in [
+            (TaskStdin::File(input), "from a file\n"),
+            (TaskStdin::String("from a string".into()), "from a string"),
+        ] {
+            let options = ExecuteTaskOptions {
+                stdin,
+                ..Default::default()
+            };
+            execute_task(&task, &HashMap::new(), &options)
+                .await
+                .unwrap();
+            assert_eq!(
+                fs_err::read_to_string(tmp.path().join("stdin.txt")).unwrap(),
+                expected
+            );
+        }
+
+        // A missing file is an error
+        let options = ExecuteTaskOptions {
+            stdin: TaskStdin::File(tmp.path().join("missing.txt")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            execute_task(&task, &HashMap::new(), &options).await,
+            Err(TaskExecutionError::OpenStdin(..))
+        ));
+    }
+}
@@ -0,0 +1,385 @@
+//! Reports the results and writes the output of tasks, see `pixi run
+//! --output-format` and `pixi run --log-file`.
+
+use std::{
+    io::Write,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use clap::ValueEnum;
+use deno_task_shell::{pipe, ShellPipeWriter};
+use miette::IntoDiagnostic;
+use tokio::task::JoinHandle;
+
+use crate::task::ExecutableTask;
+
+/// The formats in which `pixi run --output-format` reports the results of the
+/// tasks.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Tap,
+}
+
+/// The result of a single task, as reported with `--output-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum TaskOutcome {
+    Ok,
+    Skipped,
+    Failed(i32),
+}
+
+impl OutputFormat {
+    /// Returns the line that reports the outcome of the `number`th task, or
+    /// `None` if this format doesn't report results. The run id and the peak
+    /// memory usage are only part of the JSON output.
+    fn result_line(
+        self,
+        number: usize,
+        run_id: &str,
+        task: &str,
+        environment: &str,
+        outcome: TaskOutcome,
+        peak_rss: Option<u64>,
+    ) -> Option<String> {
+        match self {
+            OutputFormat::Text => None,
+            OutputFormat::Json => {
+                let (status, exit_code) = match outcome {
+                    TaskOutcome::Ok => ("ok", Some(0)),
+                    TaskOutcome::Skipped => ("skipped", None),
+                    TaskOutcome::Failed(code) => ("failed", Some(code)),
+                };
+                let mut result = serde_json::json!({
+                    "run_id": run_id,
+                    "task": task,
+                    "environment": environment,
+                    "status": status,
+                    "exit_code": exit_code,
+                });
+                if let Some(peak_rss) = peak_rss {
+                    result["peak_rss_bytes"] = peak_rss.into();
+                }
+                Some(result.to_string())
+            }
+            OutputFormat::Tap => Some(match outcome {
+                TaskOutcome::Ok => format!("ok {number} - {task}"),
+                TaskOutcome::Skipped => format!("ok {number} - {task} # SKIP cache hit"),
+                TaskOutcome::Failed(_) => format!("not ok {number} - {task}"),
+            }),
+        }
+    }
+}
+
+/// Reports the results of the tasks on stdout in the format selected with
+/// `--output-format`. Shared between the environments the tasks run in.
+#[derive(Default)]
+pub(super) struct TaskReport {
+    pub(super) format: OutputFormat,
+    run_id: String,
+    reported: AtomicUsize,
+}
+
+impl TaskReport {
+    pub(super) fn new(format: OutputFormat, run_id: String) -> Self {
+        Self {
+            format,
+            run_id,
+            reported: AtomicUsize::new(0),
+        }
+    }
+
+    /// Reports the outcome of a task, with its peak memory usage if it was
+    /// measured.
+    pub(super) fn task_finished(
+        &self,
+        task: &ExecutableTask<'_>,
+        outcome: TaskOutcome,
+        peak_rss: Option<u64>,
+    ) {
+        let number = self.reported.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(line) = self.format.result_line(
+            number,
+            &self.run_id,
+            task.name().unwrap_or("unnamed"),
+            task.run_environment.name().as_str(),
+            outcome,
+            peak_rss,
+        ) {
+            println!("{line}");
+        }
+    }
+
+    /// Finishes the report, for TAP this prints the plan with the number of
+    /// reported tasks.
+    pub(super) fn finish(&self) {
+        if self.format == OutputFormat::Tap {
+            println!("1..{}", self.reported.load(Ordering::Relaxed));
+        }
+    }
+}
+
+/// The file that the output of the tasks is written to, shared between the
+/// stdout and stderr of all tasks.
+pub(super) type LogFile = Arc<Mutex<fs_err::File>>;
+
+/// Opens the file passed to `--log-file`.
+pub(super) fn open_log_file(path: &Path, append: bool) -> miette::Result<LogFile> {
+    let file = fs_err::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .into_diagnostic()?;
+    Ok(Arc::new(Mutex::new(file)))
+}
+
+/// A writer that writes everything to the terminal and to the log file.
+struct TeeWriter<W> {
+    terminal: W,
+    log_file: LogFile,
+}
+
+impl<W: Write> Write for TeeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.terminal.write_all(buf)?;
+        self.terminal.flush()?;
+        self.log_file
+            .lock()
+            .expect("log file lock is poisoned")
+            .write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.terminal.flush()?;
+        self.log_file
+            .lock()
+            .expect("log file lock is poisoned")
+            .flush()
+    }
+}
+
+/// A writer that turns everything written to it into TAP comments by
+/// prefixing every line with `# `.
+struct TapCommentWriter<W> {
+    inner: W,
+    at_line_start: bool,
+}
+
+impl<W: Write> TapCommentWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            at_line_start: true,
+        }
+    }
+
+    /// Terminates the last comment if the output didn't end with a newline,
+    /// so that the next line of the TAP stream starts on its own line.
+    fn finish(&mut self) -> std::io::Result<()> {
+        if !self.at_line_start {
+            self.inner.write_all(b"\n")?;
+            self.at_line_start = true;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Write for TapCommentWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for line in buf.split_inclusive(|&byte| byte == b'\n') {
+            if self.at_line_start {
+                self.inner.write_all(b"# ")?;
+            }
+            self.inner.write_all(line)?;
+            self.at_line_start = line.ends_with(b"\n");
+        }
+        self.inner.flush()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns a writer for the stdout of a task that writes it as TAP comments to
+/// the terminal. The log file, if there is one, receives the output as is.
+pub(super) fn tap_output_writer(
+    log_file: Option<&LogFile>,
+) -> (ShellPipeWriter, Option<JoinHandle<()>>) {
+    let (reader, writer) = pipe();
+    let log_file = log_file.cloned();
+    let handle = tokio::task::spawn_blocking(move || {
+        let mut comments = TapCommentWriter::new(std::io::stdout());
+        let result = match log_file {
+            Some(log_file) => reader.pipe_to(&mut TeeWriter {
+                terminal: &mut comments,
+                log_file,
+            }),
+            None => reader.pipe_to(&mut comments),
+        };
+        if let Err(err) = result {
+            tracing::warn!("failed to write the output of the task: {err}");
+        }
+        if let Err(err) = comments.finish() {
+            tracing::warn!("failed to write the output of the task: {err}");
+        }
+    });
+    (writer, Some(handle))
+}
+
+/// Returns a writer for the output of a task that writes to `terminal`, and
+/// also to the log file if there is one. The returned handle resolves when
+/// all output has been written.
+pub(super) fn output_writer<W: Write + Send + 'static>(
+    terminal: W,
+    default: ShellPipeWriter,
+    log_file: Option<&LogFile>,
+) -> (ShellPipeWriter, Option<JoinHandle<()>>) {
+    let Some(log_file) = log_file else {
+        return (default, None);
+    };
+    let (reader, writer) = pipe();
+    let mut tee = TeeWriter {
+        terminal,
+        log_file: log_file.clone(),
+    };
+    let handle = tokio::task::spawn_blocking(move || {
+        if let Err(err) = reader.pipe_to(&mut tee) {
+            tracing::warn!("failed to write the output of the task: {err}");
+        }
+    });
+    (writer, Some(handle))
+}
+
+/// Returns a writer that captures everything that is written to it before
+/// passing it on to `next`, for `--assert-output`. The returned handle
+/// resolves to the captured output when the writer is closed.
+pub(super) fn capturing_writer(
+    mut next: ShellPipeWriter,
+) -> (ShellPipeWriter, JoinHandle<Vec<u8>>) {
+    let (mut reader, writer) = pipe();
+    let handle = tokio::task::spawn_blocking(move || {
+        let mut captured = Vec::new();
+        let mut buffer = [0; 512];
+        loop {
+            let size = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(size) => size,
+                Err(err) => {
+                    tracing::warn!("failed to read the output of the task: {err}");
+                    break;
+                }
+            };
+            captured.extend_from_slice(&buffer[..size]);
+            if let Err(err) = next.write_all(&buffer[..size]) {
+                tracing::warn!("failed to write the output of the task: {err}");
+            }
+        }
+        captured
+    });
+    (writer, handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::cli::run::{execute_task, harness, ExecuteTaskOptions, StdinPassthrough, TaskStdin};
+
+    #[test]
+    fn test_output_format_result_lines() {
+        let line = |format: OutputFormat, outcome| {
+            format.result_line(2, "run", "build", "default", outcome, None)
+        };
+
+        assert_eq!(line(OutputFormat::Text, TaskOutcome::Ok), None);
+
+        assert_eq!(
+            line(OutputFormat::Tap, TaskOutcome::Ok).as_deref(),
+            Some("ok 2 - build")
+        );
+        assert_eq!(
+            line(OutputFormat::Tap, TaskOutcome::Skipped).as_deref(),
+            Some("ok 2 - build # SKIP cache hit")
+        );
+        assert_eq!(
+            line(OutputFormat::Tap, TaskOutcome::Failed(3)).as_deref(),
+            Some("not ok 2 - build")
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&line(OutputFormat::Json, TaskOutcome::Failed(3)).unwrap())
+                .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "run_id": "run",
+                "task": "build",
+                "environment": "default",
+                "status": "failed",
+                "exit_code": 3,
+            })
+        );
+
+        let json: serde_json::Value = serde_json::from_str(
+            &OutputFormat::Json
+                .result_line(1, "run", "build", "default", TaskOutcome::Ok, Some(1024))
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["peak_rss_bytes"], 1024);
+    }
+
+    #[test]
+    fn test_tap_comment_writer() {
+        let mut output = Vec::new();
+        let mut comments = TapCommentWriter::new(&mut output);
+        comments.write_all(b"hello\nwor").unwrap();
+        comments.write_all(b"ld\n\nno newline").unwrap();
+        comments.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "# hello\n# world\n# \n# no newline\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_log_file_contains_task_output() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workspace = harness::workspace(
+            &tmp.path().join("pixi.toml"),
+            r#"
+        [tasks]
+        hello = "echo hello"
+    "#,
+        );
+        let task = harness::planned_task(&workspace, "hello");
+
+        let log_path = tmp.path().join("task.log");
+        fs_err::write(&log_path, "previous run\n").unwrap();
+        for append in [false, true] {
+            let options = ExecuteTaskOptions {
+                stdin: TaskStdin::Passthrough(StdinPassthrough::Null),
+                log_file: Some(open_log_file(&log_path, append).unwrap()),
+                ..Default::default()
+            };
+            execute_task(&task, &HashMap::new(), &options)
+                .await
+                .unwrap();
+        }
+
+        let log = fs_err::read_to_string(&log_path).unwrap();
+        assert_eq!(log, "hello\nhello\n");
+    }
+}
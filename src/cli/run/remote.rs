@@ -0,0 +1,173 @@
+//! Looks up the task of a remote manifest, see `pixi run --task-url`.
+
+use std::path::Path;
+
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use pixi_consts::consts;
+use pixi_manifest::{
+    task::{CmdArgs, Custom},
+    FeaturesExt, TaskName,
+};
+use rattler_digest::{compute_bytes_digest, parse_digest_from_hex, Sha256};
+use url::Url;
+
+use crate::Workspace;
+
+/// Downloads the remote manifest passed to `--task-url`.
+pub(super) async fn fetch_remote_manifest(
+    workspace: &Workspace,
+    url: &Url,
+) -> miette::Result<String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        miette::bail!("only HTTP and HTTPS task URLs are supported, got '{url}'");
+    }
+    workspace
+        .authenticated_client()?
+        .get(url.clone())
+        .send()
+        .await
+        .into_diagnostic()?
+        .error_for_status()
+        .into_diagnostic()?
+        .text()
+        .await
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to download the manifest from '{url}'"))
+}
+
+/// Verifies the hash of a remote manifest and returns the task with the given
+/// name as a custom command, which runs in the current directory.
+pub(super) fn remote_task(
+    content: &str,
+    expected_hash: Option<&str>,
+    name: &str,
+) -> miette::Result<Custom> {
+    if let Some(expected_hash) = expected_hash {
+        let expected = parse_digest_from_hex::<Sha256>(expected_hash)
+            .ok_or_else(|| miette::miette!("'{expected_hash}' is not a valid SHA256 hash"))?;
+        let actual = compute_bytes_digest::<Sha256>(content);
+        if actual != expected {
+            miette::bail!(
+                "the hash of the remote manifest is {actual:x}, but {expected:x} was expected"
+            );
+        }
+    }
+
+    let remote = Workspace::from_str(Path::new(consts::WORKSPACE_MANIFEST), content)?;
+    let task = remote
+        .default_environment()
+        .task(&TaskName::from(name), None)
+        .map_err(|_| miette::miette!("the remote manifest does not define the task '{name}'"))?;
+    let unsupported = [
+        ("depends-on", !task.depends_on().is_empty()),
+        ("cwd", task.working_directory().is_some()),
+        ("env", task.env().is_some()),
+        ("inputs", task.inputs().is_some()),
+        ("outputs", task.outputs().is_some()),
+        ("clean-env", task.clean_env()),
+        ("tempdir", task.tempdir()),
+        ("condition", task.condition().is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(field, used)| used.then_some(field))
+    .collect_vec();
+    if !unsupported.is_empty() {
+        miette::bail!(
+            "the remote task '{name}' sets {}, which is not supported with `--task-url`",
+            unsupported
+                .iter()
+                .map(|field| format!("`{field}`"))
+                .join(", ")
+        );
+    }
+    let command = task
+        .as_single_command()
+        .ok_or_else(|| miette::miette!("the remote task '{name}' does not define a command"))?;
+
+    // The task runs in the environment of the current workspace, it would miss
+    // the dependencies of the remote manifest.
+    let environment = remote.default_environment();
+    let platform = Some(environment.best_platform());
+    if !environment.combined_dependencies(platform).is_empty()
+        || !environment.pypi_dependencies(platform).is_empty()
+    {
+        miette::bail!(
+            help = "add the dependencies to the current workspace and run the command as a task",
+            "the remote manifest of the task '{name}' declares dependencies, which is not supported with `--task-url`"
+        );
+    }
+
+    Ok(Custom {
+        cmd: CmdArgs::Single(command.into_owned()),
+        cwd: std::env::current_dir().ok(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::run::{harness, planned_tasks, task_graph, Args},
+        task::SearchEnvironments,
+    };
+
+    #[test]
+    fn test_remote_task() {
+        let content = r#"
+        [project]
+        name = "remote"
+        channels = []
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        hello = "echo hello && echo world"
+        all = { depends-on = ["hello"] }
+        build = { cmd = "make", cwd = "src", env = { CC = "gcc" } }
+    "#;
+        let hash = format!("{:x}", compute_bytes_digest::<Sha256>(content));
+        let command = |task: Custom| task.cmd.as_single().into_owned();
+
+        assert_eq!(
+            command(remote_task(content, None, "hello").unwrap()),
+            "echo hello && echo world"
+        );
+        assert_eq!(
+            command(remote_task(content, Some(&hash), "hello").unwrap()),
+            "echo hello && echo world"
+        );
+        assert!(remote_task(content, Some(&"0".repeat(64)), "hello").is_err());
+        assert!(remote_task(content, Some("not-a-hash"), "hello").is_err());
+        assert!(remote_task(content, None, "missing").is_err());
+        assert!(remote_task(content, None, "all").is_err());
+        let err = remote_task(content, None, "build").unwrap_err();
+        assert!(err.to_string().contains("`cwd`, `env`"));
+
+        // The dependencies of a remote manifest can't be installed.
+        let with_dependencies = format!("{content}\n[dependencies]\npython = \"*\"\n");
+        let err = remote_task(&with_dependencies, None, "hello").unwrap_err();
+        assert!(err.to_string().contains("declares dependencies"));
+
+        // The remote task is not looked up in the workspace, even if a local task
+        // has the same name as its command.
+        let workspace = harness::workspace(
+            Path::new("pixi.toml"),
+            r#"
+        [tasks]
+        echo = "echo local"
+    "#,
+        );
+        let search_envs = SearchEnvironments::from_opt_env(&workspace, None, None);
+        let args = Args {
+            task: vec!["hello".to_string(), "again".to_string()],
+            ..Args::default()
+        };
+        let remote = remote_task(content, None, "hello").unwrap();
+        let task_graph = task_graph(&args, &workspace, &search_envs, Some(&remote)).unwrap();
+        let task = planned_tasks(&task_graph, false).remove(0);
+        assert!(task.task().is_custom());
+        assert_eq!(
+            task.full_command().unwrap().unwrap(),
+            "echo hello && echo world again"
+        );
+    }
+}
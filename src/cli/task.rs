@@ -1,7 +1,7 @@
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     error::Error,
-    path::PathBuf,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
@@ -12,11 +12,12 @@ use itertools::Itertools;
 use miette::IntoDiagnostic;
 use pixi_manifest::{
     task::{quote, Alias, CmdArgs, Execute, Task, TaskName},
-    EnvironmentName, FeatureName,
+    EnvironmentName, FeatureName, TargetSelector, WorkspaceManifest,
 };
 use rattler_conda_types::Platform;
 use serde::Serialize;
 use serde_with::serde_as;
+use toml_edit::{ImDocument, Item, TableLike};
 
 use crate::workspace::virtual_packages::verify_current_platform_can_run_environment;
 use crate::{
@@ -42,6 +43,21 @@ pub enum Operation {
     /// List all tasks in the workspace
     #[clap(visible_alias = "ls", alias = "l")]
     List(ListArgs),
+
+    /// Open the definition of a task in your editor
+    Edit(EditArgs),
+}
+
+#[derive(Parser, Debug)]
+#[clap(arg_required_else_help = true)]
+pub struct EditArgs {
+    /// Task name to edit.
+    pub name: TaskName,
+
+    /// The editor to use, defaults to the `VISUAL` or `EDITOR` environment
+    /// variable or `nano` on Unix and `notepad` on Windows
+    #[arg(long)]
+    pub editor: Option<String>,
 }
 
 #[derive(Parser, Debug)]
@@ -58,6 +74,11 @@ pub struct RemoveArgs {
     /// The feature for which the task should be removed.
     #[arg(long, short)]
     pub feature: Option<String>,
+
+    /// Also remove the references to the removed tasks from the `depends-on`
+    /// of other tasks.
+    #[arg(long)]
+    pub prune_dependents: bool,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -103,7 +124,9 @@ pub struct AddArgs {
 }
 
 /// Parse a single key-value pair
-fn parse_key_val(s: &str) -> Result<(String, String), Box<dyn Error + Send + Sync + 'static>> {
+pub(crate) fn parse_key_val(
+    s: &str,
+) -> Result<(String, String), Box<dyn Error + Send + Sync + 'static>> {
     let pos = s
         .find('=')
         .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{}`", s))?;
@@ -211,6 +234,9 @@ impl From<AddArgs> for Task {
                 env,
                 description,
                 clean_env,
+                env_merge_strategy: Default::default(),
+                tempdir: false,
+                condition: None,
             })
         }
     }
@@ -292,7 +318,122 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         Operation::Remove(args) => remove_tasks(workspace.modify()?, args).await,
         Operation::Alias(args) => alias_task(workspace.modify()?, args).await,
         Operation::List(args) => list_tasks(workspace, args).await,
+        Operation::Edit(args) => edit_task(workspace, args).await,
+    }
+}
+
+async fn edit_task(workspace: Workspace, args: EditArgs) -> miette::Result<()> {
+    let manifest_path = workspace.workspace.provenance.path.clone();
+    let content = fs_err::read_to_string(&manifest_path).into_diagnostic()?;
+    let line = task_definition_line(&content, args.name.as_str()).ok_or_else(|| {
+        miette::miette!(
+            "task '{}' is not defined in '{}'",
+            args.name.fancy_display(),
+            manifest_path.display()
+        )
+    })?;
+
+    let editor = args
+        .editor
+        .or_else(|| std::env::var("VISUAL").ok())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .unwrap_or_else(|| {
+            if cfg!(windows) {
+                "notepad".to_string()
+            } else {
+                "nano".to_string()
+            }
+        });
+
+    let status = editor_command(&editor, &manifest_path, line)?
+        .status()
+        .into_diagnostic()?;
+    if !status.success() {
+        miette::bail!("the editor '{editor}' exited with {status}");
+    }
+
+    // Only validate the manifest, if it is invalid the changes are kept so they
+    // can be fixed instead of being lost.
+    Workspace::from_path(&manifest_path).map_err(|err| {
+        miette::Report::from(err).wrap_err(format!(
+            "the manifest is no longer valid, fix it in '{}'",
+            manifest_path.display()
+        ))
+    })?;
+    eprintln!(
+        "{}Edited task `{}`",
+        console::style(console::Emoji("✔ ", "+")).green(),
+        args.name.fancy_display().bold(),
+    );
+    Ok(())
+}
+
+/// Returns the 1-based line on which the task with the given name is defined
+/// in the manifest, searching the `tasks` tables of the workspace, features and
+/// targets.
+fn task_definition_line(content: &str, name: &str) -> Option<usize> {
+    fn find(table: &dyn TableLike, name: &str) -> Option<usize> {
+        let task_key = table
+            .get("tasks")
+            .and_then(Item::as_table_like)
+            .and_then(|tasks| tasks.key(name));
+        if let Some(key) = task_key {
+            return key.span().map(|span| span.start);
+        }
+        table
+            .iter()
+            .filter_map(|(_, item)| item.as_table_like())
+            .find_map(|table| find(table, name))
+    }
+
+    let document = ImDocument::parse(content).ok()?;
+    let offset = find(document.as_table(), name)?;
+    Some(content[..offset].matches('\n').count() + 1)
+}
+
+/// Returns the command that opens the manifest at the given line in the
+/// editor. The editor can contain arguments, e.g. `code --wait`.
+fn editor_command(
+    editor: &str,
+    manifest_path: &Path,
+    line: usize,
+) -> miette::Result<std::process::Command> {
+    let words = shlex::split(editor)
+        .filter(|words| !words.is_empty())
+        .ok_or_else(|| miette::miette!("the editor '{editor}' is not a valid command"))?;
+    let mut command = if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        command.arg("/C").args(&words);
+        command
+    } else {
+        let mut command = std::process::Command::new(&words[0]);
+        command.args(&words[1..]);
+        command
+    };
+    if editor_supports_line_argument(&words[0]) {
+        command.arg(format!("+{line}"));
     }
+    command.arg(manifest_path);
+    Ok(command)
+}
+
+/// True if the editor accepts a `+<line>` argument to open a file at a line.
+fn editor_supports_line_argument(editor: &str) -> bool {
+    let Some(name) = Path::new(editor).file_stem().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    matches!(
+        name,
+        "vi" | "vim"
+            | "nvim"
+            | "gvim"
+            | "nano"
+            | "pico"
+            | "emacs"
+            | "emacsclient"
+            | "micro"
+            | "kak"
+    )
 }
 
 async fn list_tasks(workspace: Workspace, args: ListArgs) -> miette::Result<()> {
@@ -440,6 +581,36 @@ async fn remove_tasks(mut workspace: WorkspaceMut, args: RemoveArgs) -> miette::
         removed.push(name);
     }
 
+    // Find the tasks that depend on a task that no longer exists, and either
+    // warn about them or remove the orphaned references.
+    let orphaned = orphaned_dependents(&workspace.workspace().workspace.value, &removed);
+    for dependent in orphaned {
+        if args.prune_dependents {
+            workspace.manifest().remove_task_dependencies(
+                &dependent.name,
+                &dependent.missing,
+                dependent.selector.as_ref(),
+                &dependent.feature,
+            )?;
+            eprintln!(
+                "{}Removed the dependencies {} from task `{}`",
+                console::style(console::Emoji("✔ ", "+")).green(),
+                dependent
+                    .missing
+                    .iter()
+                    .map(|name| name.fancy_display())
+                    .format(", "),
+                dependent.name.fancy_display().bold(),
+            );
+        } else {
+            tracing::warn!(
+                "task '{}' depends on the removed task(s) {}, use `--prune-dependents` to remove these references",
+                dependent.name.fancy_display(),
+                dependent.missing.iter().map(|name| name.fancy_display()).format(", "),
+            );
+        }
+    }
+
     workspace.save().await.into_diagnostic()?;
 
     for name in removed {
@@ -453,6 +624,56 @@ async fn remove_tasks(mut workspace: WorkspaceMut, args: RemoveArgs) -> miette::
     Ok(())
 }
 
+/// A task that depends on one or more tasks that no longer exist.
+struct OrphanedDependent {
+    name: TaskName,
+    feature: FeatureName,
+    selector: Option<TargetSelector>,
+    /// The dependencies of the task that no longer exist.
+    missing: Vec<TaskName>,
+}
+
+/// Returns the tasks in the manifest that depend on one of the `removed`
+/// tasks, if that task is no longer defined anywhere in the manifest. The
+/// tasks are sorted by name.
+fn orphaned_dependents(
+    manifest: &WorkspaceManifest,
+    removed: &[&TaskName],
+) -> Vec<OrphanedDependent> {
+    let tasks = manifest
+        .features
+        .values()
+        .flat_map(|feature| {
+            feature.targets.iter().flat_map(move |(target, selector)| {
+                target
+                    .tasks
+                    .iter()
+                    .map(move |(name, task)| (feature, selector, name, task))
+            })
+        })
+        .collect_vec();
+    let defined: HashSet<&TaskName> = tasks.iter().map(|(_, _, name, _)| *name).collect();
+
+    tasks
+        .into_iter()
+        .filter_map(|(feature, selector, name, task)| {
+            let missing = task
+                .depends_on()
+                .iter()
+                .filter(|dep| removed.contains(dep) && !defined.contains(dep))
+                .cloned()
+                .collect_vec();
+            (!missing.is_empty()).then(|| OrphanedDependent {
+                name: name.clone(),
+                feature: feature.name.clone(),
+                selector: selector.cloned(),
+                missing,
+            })
+        })
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect()
+}
+
 async fn add_task(mut workspace: WorkspaceMut, args: AddArgs) -> miette::Result<()> {
     let name = &args.name;
     let task: Task = args.clone().into();
@@ -570,3 +791,64 @@ impl From<&Task> for TaskInfo {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_definition_line() {
+        let content = r#"[workspace]
+name = "pixi"
+channels = []
+platforms = ["linux-64"]
+
+[tasks]
+build = "cargo build"
+
+[tasks.test]
+cmd = "cargo test"
+
+[feature.lint.tasks]
+lint = "cargo clippy"
+
+[feature.lint.target.linux-64.tasks]
+fmt = "cargo fmt"
+"#;
+        assert_eq!(task_definition_line(content, "build"), Some(7));
+        assert_eq!(task_definition_line(content, "test"), Some(9));
+        assert_eq!(task_definition_line(content, "lint"), Some(13));
+        assert_eq!(task_definition_line(content, "fmt"), Some(16));
+        assert_eq!(task_definition_line(content, "unknown"), None);
+    }
+
+    #[test]
+    fn test_editor_supports_line_argument() {
+        assert!(editor_supports_line_argument("vim"));
+        assert!(editor_supports_line_argument("/usr/bin/nano"));
+        assert!(!editor_supports_line_argument("notepad"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_editor_command() {
+        let manifest_path = Path::new("pixi.toml");
+        let args = |command: &std::process::Command| {
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect_vec()
+        };
+
+        let command = editor_command("code --wait", manifest_path, 12).unwrap();
+        assert_eq!(command.get_program(), "code");
+        assert_eq!(args(&command), ["--wait", "pixi.toml"]);
+
+        let command = editor_command("'/opt/my editor/vim' -u NONE", manifest_path, 12).unwrap();
+        assert_eq!(command.get_program(), "/opt/my editor/vim");
+        assert_eq!(args(&command), ["-u", "NONE", "+12", "pixi.toml"]);
+
+        assert!(editor_command("", manifest_path, 12).is_err());
+        assert!(editor_command("'vim", manifest_path, 12).is_err());
+    }
+}
@@ -13,15 +13,16 @@ use std::{
 };
 
 use dialoguer::theme::ColorfulTheme;
-use miette::{Context, IntoDiagnostic};
+use miette::{Context, Diagnostic, IntoDiagnostic};
 use pixi_consts::consts;
 use pixi_git::credentials::store_credentials_from_url;
-use pixi_manifest::{FeaturesExt, PyPiRequirement};
+use pixi_manifest::{EnvironmentName, FeaturesExt, PyPiRequirement};
 use pixi_progress::await_in_progress;
 use pixi_spec::{GitSpec, PixiSpec};
 use rattler_conda_types::Platform;
 use rattler_lock::LockedPackageRef;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use xxhash_rust::xxh3::Xxh3;
 
 use crate::{
@@ -36,6 +37,68 @@ pub use conda_prefix::{CondaPrefixUpdated, CondaPrefixUpdater, CondaPrefixUpdate
 pub use pypi_prefix::update_prefix_pypi;
 pub use python_status::PythonStatus;
 
+/// An error that is returned when an installed environment has no prefix
+/// file, e.g. because the environment was partially removed.
+#[derive(Debug, Error, Diagnostic)]
+#[error("the prefix of the environment '{environment}' at '{}' is missing", prefix.display())]
+#[diagnostic(help("reinstall the environment with `pixi install -e {environment}`"))]
+pub struct MissingPrefixError {
+    pub environment: EnvironmentName,
+    pub prefix: PathBuf,
+}
+
+/// An error that is returned when the prefix file of an environment exists
+/// but could not be read, e.g. because it is corrupted.
+#[derive(Debug, Error, Diagnostic)]
+#[error("the prefix of the environment '{environment}' at '{}' is corrupted", prefix.display())]
+#[diagnostic(help(
+    "remove '{}' and reinstall the environment with `pixi install -e {environment}`",
+    prefix_file.display()
+))]
+pub struct CorruptedPrefixError {
+    pub environment: EnvironmentName,
+    pub prefix: PathBuf,
+    pub prefix_file: PathBuf,
+    #[source]
+    pub source: std::io::Error,
+}
+
+/// Verifies that the prefix of an installed environment is intact, so a task
+/// doesn't run in a broken environment. An environment that is not installed
+/// yet is fine, it is installed when it is needed.
+///
+/// Unlike [`verify_prefix_location_unchanged`] this never prompts, and
+/// `pixi install` doesn't run it so it can repair the environment.
+pub fn verify_environment_prefix(environment: &Environment<'_>) -> miette::Result<()> {
+    verify_prefix(environment.name(), &environment.dir())
+}
+
+fn verify_prefix(environment: &EnvironmentName, environment_dir: &Path) -> miette::Result<()> {
+    // Only an environment that was installed by pixi has an environment file.
+    if !environment_file_path(environment_dir).is_file() {
+        return Ok(());
+    }
+
+    let prefix_file = environment_dir
+        .join(consts::CONDA_META_DIR)
+        .join(consts::PREFIX_FILE_NAME);
+    match fs_err::read_to_string(&prefix_file) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(MissingPrefixError {
+            environment: environment.clone(),
+            prefix: environment_dir.to_path_buf(),
+        }
+        .into()),
+        Err(e) => Err(CorruptedPrefixError {
+            environment: environment.clone(),
+            prefix: environment_dir.to_path_buf(),
+            prefix_file,
+            source: e,
+        }
+        .into()),
+    }
+}
+
 /// Verify the location of the prefix folder is not changed so the applied
 /// prefix path is still valid. Errors when there is a file system error or the
 /// path does not align with the defined prefix. Returns false when the file is
@@ -436,3 +499,47 @@ pub type PerEnvironment<'p, T> = HashMap<Environment<'p>, T>;
 pub type PerGroup<'p, T> = HashMap<GroupedEnvironment<'p>, T>;
 pub type PerEnvironmentAndPlatform<'p, T> = PerEnvironment<'p, HashMap<Platform, T>>;
 pub type PerGroupAndPlatform<'p, T> = PerGroup<'p, HashMap<Platform, T>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_errors_suggest_remediation() {
+        let environment_dir = tempfile::tempdir().unwrap();
+        let environment = EnvironmentName::Named("test".to_string());
+        let conda_meta = environment_dir.path().join(consts::CONDA_META_DIR);
+        fs_err::create_dir_all(&conda_meta).unwrap();
+
+        // An environment that is not installed yet is fine.
+        verify_prefix(&environment, environment_dir.path()).unwrap();
+
+        // An installed environment without a prefix file is missing its prefix.
+        fs_err::write(environment_file_path(environment_dir.path()), "{}").unwrap();
+        let err = verify_prefix(&environment, environment_dir.path()).unwrap_err();
+        assert!(err.to_string().contains("'test'"));
+        assert!(err.to_string().contains("is missing"));
+        assert_eq!(
+            err.help().unwrap().to_string(),
+            "reinstall the environment with `pixi install -e test`"
+        );
+
+        // A prefix file that is not valid UTF-8 can not be read.
+        let prefix_file = conda_meta.join(consts::PREFIX_FILE_NAME);
+        fs_err::write(&prefix_file, [0xff, 0xfe]).unwrap();
+        let err = verify_prefix(&environment, environment_dir.path()).unwrap_err();
+        assert!(err.to_string().contains("'test'"));
+        assert!(err.to_string().contains("is corrupted"));
+        assert_eq!(
+            err.help().unwrap().to_string(),
+            format!(
+                "remove '{}' and reinstall the environment with `pixi install -e test`",
+                prefix_file.display()
+            )
+        );
+
+        // A readable prefix file is intact.
+        fs_err::write(&prefix_file, conda_meta.to_string_lossy().as_bytes()).unwrap();
+        verify_prefix(&environment, environment_dir.path()).unwrap();
+    }
+}
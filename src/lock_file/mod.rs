@@ -9,6 +9,7 @@ mod utils;
 pub mod virtual_packages;
 
 pub use crate::environment::CondaPrefixUpdater;
+pub(crate) use outdated::OutdatedEnvironments;
 pub(crate) use package_identifier::PypiPackageIdentifier;
 use pixi_record::PixiRecord;
 use rattler_lock::{PypiPackageData, PypiPackageEnvironmentData};
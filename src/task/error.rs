@@ -37,3 +37,22 @@ impl Diagnostic for AmbiguousTaskError {
         )))
     }
 }
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("the task '{task_name}' has an invalid {kind} glob '{glob}'", task_name = task_name.fancy_display())]
+pub struct InvalidGlobError {
+    pub task_name: TaskName,
+    /// Either `inputs` or `outputs`.
+    pub kind: &'static str,
+    pub glob: String,
+    #[source]
+    pub source: wax::BuildError,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("the task graph contains {} invalid glob pattern(s)", errors.len())]
+#[diagnostic(help("fix the `inputs` and `outputs` of the tasks in the manifest"))]
+pub struct InvalidGlobsError {
+    #[related]
+    pub errors: Vec<InvalidGlobError>,
+}
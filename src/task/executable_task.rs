@@ -6,21 +6,26 @@ use std::{
 };
 
 use deno_task_shell::{
-    execute_with_pipes, parser::SequentialList, pipe, ShellPipeWriter, ShellState,
+    execute_with_pipes,
+    parser::{Command, CommandInner, Pipeline, PipelineInner, Sequence, SequentialList},
+    pipe, ShellPipeWriter, ShellState,
 };
 use fs_err::tokio as tokio_fs;
 use itertools::Itertools;
 use miette::{Context, Diagnostic};
 use pixi_consts::consts;
-use pixi_manifest::{Task, TaskName};
+use pixi_manifest::{EnvMergeStrategy, Task, TaskName};
 use pixi_progress::await_in_progress;
 use rattler_lock::LockFile;
 use thiserror::Error;
 use tokio::task::JoinHandle;
 
-use super::task_hash::{InputHashesError, TaskCache, TaskHash};
+use super::task_hash::{ComputationHash, InputHashesError, TaskCache, TaskHash};
 use crate::{
-    activation::CurrentEnvVarBehavior,
+    activation::{
+        get_inherited_prefix_environment_variables, get_prefix_only_environment_variables,
+        CurrentEnvVarBehavior,
+    },
     lock_file::LockFileDerivedData,
     task::task_graph::{TaskGraph, TaskId},
     workspace::get_activated_environment_variables,
@@ -44,9 +49,22 @@ pub struct FailedToParseShellScript {
 }
 
 #[derive(Debug, Error, Diagnostic)]
-#[error("invalid working directory '{path}'")]
-pub struct InvalidWorkingDirectory {
-    pub path: String,
+pub enum InvalidWorkingDirectory {
+    #[error("invalid working directory '{path}'")]
+    NotADirectory { path: String },
+
+    #[error("the working directory '{}' of the task is outside of the workspace root '{}'", .path.display(), .root.display())]
+    #[diagnostic(help("set `cwd` of the task to a directory inside the workspace"))]
+    EscapesWorkspaceRoot { path: PathBuf, root: PathBuf },
+}
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("the environment variables {} of the task are also set by the activation of the environment", .variables.iter().map(|v| format!("'{v}'")).join(", "))]
+#[diagnostic(help(
+    "set `env-merge-strategy` of the task to `task-wins` or `activation-wins` to choose which value is used"
+))]
+pub struct EnvMergeConflict {
+    pub variables: Vec<String>,
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -56,6 +74,9 @@ pub enum TaskExecutionError {
 
     #[error(transparent)]
     FailedToParseShellScript(#[from] FailedToParseShellScript),
+
+    #[error(transparent)]
+    EnvMergeConflict(#[from] EnvMergeConflict),
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -71,10 +92,120 @@ pub enum CacheUpdateError {
 }
 
 pub enum CanSkip {
-    Yes,
+    Yes(SkipReason),
     No(Option<TaskHash>),
 }
 
+/// Describes why a task can be skipped, its cache is still valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkipReason {
+    /// The hash of the task, which matches the hash stored in its cache.
+    pub hash: ComputationHash,
+    /// The number of input files that are unchanged, if the task has inputs.
+    pub inputs: Option<usize>,
+    /// The number of output files that are present, if the task has outputs.
+    pub outputs: Option<usize>,
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(inputs) = self.inputs {
+            parts.push(format!("{inputs} input file(s) unchanged"));
+        }
+        if let Some(outputs) = self.outputs {
+            parts.push(format!("{outputs} output file(s) present"));
+        }
+        write!(f, "{} (hash {})", parts.join(", "), self.hash)
+    }
+}
+
+/// Where the arguments that follow the task name on the command line end up in
+/// the command of the task, see `pixi run --task-precedence`.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TaskPrecedence {
+    /// The arguments are appended to the command, so they take precedence
+    /// over the arguments in the manifest.
+    #[default]
+    Cli,
+    /// The arguments are inserted directly after the program of the last
+    /// command, so the arguments in the manifest take precedence.
+    Manifest,
+    /// The arguments replace the command of the task.
+    CliReplaces,
+}
+
+impl TaskPrecedence {
+    /// Combines the command of a task with the arguments from the command
+    /// line.
+    fn combine(self, command: &str, args: &str) -> Result<String, FailedToParseShellScript> {
+        Ok(match self {
+            TaskPrecedence::Cli => format!("{command} {args}"),
+            _ if args.is_empty() => command.to_string(),
+            TaskPrecedence::Manifest => insert_after_program(command, args)?,
+            TaskPrecedence::CliReplaces => args.to_string(),
+        })
+    }
+}
+
+/// Inserts the arguments after the program of the last command that runs
+/// sequentially, e.g. `cargo build && FOO=1 cargo test` becomes
+/// `cargo build && FOO=1 cargo <args> test`.
+///
+/// The end of the program is the shortest prefix of the command that parses
+/// to the same commands with only the program left in the last command, so
+/// quotes and environment variables are handled like deno task shell does.
+fn insert_after_program(command: &str, args: &str) -> Result<String, FailedToParseShellScript> {
+    let error = |error: String| FailedToParseShellScript {
+        script: command.to_string(),
+        error,
+    };
+    let unsupported = || {
+        error("the arguments can only be inserted after the program of a simple command, use `--task-precedence cli` to append them".to_string())
+    };
+
+    let mut expected = deno_task_shell::parser::parse(command).map_err(|e| error(e.to_string()))?;
+    let last = expected
+        .items
+        .last_mut()
+        .filter(|item| !item.is_async)
+        .and_then(|item| last_simple_command(&mut item.sequence))
+        .ok_or_else(unsupported)?;
+    if let CommandInner::Simple(simple) = &mut last.inner {
+        simple.args.truncate(1);
+    }
+    last.redirect = None;
+
+    let end = command
+        .char_indices()
+        .map(|(idx, _)| idx)
+        .chain([command.len()])
+        .filter(|&idx| {
+            idx == command.len()
+                || command[idx..].starts_with(|c: char| c.is_whitespace() || ";&|<>".contains(c))
+        })
+        .find(|&idx| {
+            deno_task_shell::parser::parse(&command[..idx]).is_ok_and(|list| list == expected)
+        })
+        .ok_or_else(unsupported)?;
+    Ok(format!("{} {args}{}", &command[..end], &command[end..]))
+}
+
+/// Returns the command that runs last in the sequence, if it is a simple
+/// command with a program.
+fn last_simple_command(sequence: &mut Sequence) -> Option<&mut Command> {
+    match sequence {
+        Sequence::BooleanList(list) => last_simple_command(&mut list.next),
+        Sequence::Pipeline(Pipeline {
+            negated: false,
+            inner: PipelineInner::Command(command),
+        }) if matches!(&command.inner, CommandInner::Simple(simple) if !simple.args.is_empty()) => {
+            Some(command)
+        }
+        _ => None,
+    }
+}
+
 /// A task that contains enough information to be able to execute it. The
 /// lifetime [`'p`] refers to the lifetime of the project that contains the
 /// tasks.
@@ -85,6 +216,13 @@ pub struct ExecutableTask<'p> {
     pub task: Cow<'p, Task>,
     pub run_environment: Environment<'p>,
     pub additional_args: Vec<String>,
+    /// The directory to store the cache of the task in, if it differs from the
+    /// task cache folder of the workspace.
+    pub task_cache_dir: Option<PathBuf>,
+    /// Where the additional arguments end up in the command of the task.
+    pub task_precedence: TaskPrecedence,
+    /// A command that the command of the task is passed to, e.g. a debugger.
+    pub command_prefix: Option<String>,
 }
 
 impl<'p> ExecutableTask<'p> {
@@ -97,9 +235,54 @@ impl<'p> ExecutableTask<'p> {
             task: node.task.clone(),
             run_environment: node.run_environment.clone(),
             additional_args: node.additional_args.clone(),
+            task_cache_dir: None,
+            task_precedence: TaskPrecedence::default(),
+            command_prefix: None,
         }
     }
 
+    /// Stores the cache of the task in the given directory instead of the
+    /// task cache folder of the workspace.
+    pub fn with_task_cache_dir(self, task_cache_dir: Option<PathBuf>) -> Self {
+        Self {
+            task_cache_dir,
+            ..self
+        }
+    }
+
+    /// Places the additional arguments in the command of the task according
+    /// to the given precedence.
+    pub fn with_task_precedence(self, task_precedence: TaskPrecedence) -> Self {
+        Self {
+            task_precedence,
+            ..self
+        }
+    }
+
+    /// Runs the command of the task through the given command, e.g.
+    /// `gdb --args`.
+    pub fn with_command_prefix(self, command_prefix: impl Into<String>) -> Self {
+        Self {
+            command_prefix: Some(command_prefix.into()),
+            ..self
+        }
+    }
+
+    /// Prepends the command prefix, if any, to the given command.
+    fn prefixed(&self, command: String) -> String {
+        match &self.command_prefix {
+            Some(prefix) => format!("{prefix} {command}"),
+            None => command,
+        }
+    }
+
+    /// Returns the directory that the cache of the task is stored in.
+    pub(crate) fn task_cache_folder(&self) -> PathBuf {
+        self.task_cache_dir
+            .clone()
+            .unwrap_or_else(|| self.project().task_cache_folder())
+    }
+
     /// Returns the name of the task or `None` if this is an anonymous task.
     pub(crate) fn name(&self) -> Option<&str> {
         self.name.as_ref().map(|name| name.as_str())
@@ -116,12 +299,17 @@ impl<'p> ExecutableTask<'p> {
     }
 
     /// Returns the task as script
-    fn as_script(&self) -> Option<String> {
+    fn as_script(
+        &self,
+        command_env: &HashMap<String, String>,
+    ) -> Result<Option<String>, FailedToParseShellScript> {
         // Convert the task into an executable string
-        let task = self.task.as_single_command()?;
+        let Some(task) = self.task.as_single_command() else {
+            return Ok(None);
+        };
 
         // Get the export specific environment variables
-        let export = get_export_specific_task_env(self.task.as_ref());
+        let export = get_export_specific_task_env(self.task.as_ref(), command_env);
 
         // Append the command line arguments verbatim
         let cli_args = self
@@ -129,14 +317,16 @@ impl<'p> ExecutableTask<'p> {
             .iter()
             .format_with(" ", |arg, f| f(&format_args!("'{}'", arg)));
 
+        let command = self.prefixed(self.task_precedence.combine(&task, &cli_args.to_string())?);
+
         // Skip the export if it's empty, to avoid newlines
         let full_script = if export.is_empty() {
-            format!("{task} {cli_args}")
+            command
         } else {
-            format!("{export}\n{task} {cli_args}")
+            format!("{export}\n{command}")
         };
 
-        Some(full_script)
+        Ok(Some(full_script))
     }
 
     /// Returns a [`SequentialList`] which can be executed by deno task shell.
@@ -144,8 +334,9 @@ impl<'p> ExecutableTask<'p> {
     /// an alias.
     pub(crate) fn as_deno_script(
         &self,
+        command_env: &HashMap<String, String>,
     ) -> Result<Option<SequentialList>, FailedToParseShellScript> {
-        if let Some(full_script) = self.as_script() {
+        if let Some(full_script) = self.as_script(command_env)? {
             tracing::debug!("Parsing shell script: {}", full_script);
 
             // Parse the shell command
@@ -160,21 +351,54 @@ impl<'p> ExecutableTask<'p> {
         }
     }
 
-    /// Returns the working directory for this task.
+    /// Returns an error if the task uses [`EnvMergeStrategy::Error`] and sets
+    /// variables that are also set by the activation in `command_env`.
+    pub(crate) fn check_env_merge(
+        &self,
+        command_env: &HashMap<String, String>,
+    ) -> Result<(), EnvMergeConflict> {
+        if self.task.env_merge_strategy() != EnvMergeStrategy::Error {
+            return Ok(());
+        }
+        let variables = activation_conflicts(self.task.as_ref(), command_env)
+            .cloned()
+            .collect_vec();
+        if variables.is_empty() {
+            Ok(())
+        } else {
+            Err(EnvMergeConflict { variables })
+        }
+    }
+
+    /// Returns the working directory for this task. The `cwd` of a task in
+    /// the manifest has to be inside the workspace root, after resolving `..`
+    /// components and symlinks. Custom commands run in the directory pixi was
+    /// started from, which may be outside of the workspace.
     pub(crate) fn working_directory(&self) -> Result<PathBuf, InvalidWorkingDirectory> {
-        Ok(match self.task.working_directory() {
-            Some(cwd) if cwd.is_absolute() => cwd.to_path_buf(),
-            Some(cwd) => {
-                let abs_path = self.workspace.root().join(cwd);
-                if !abs_path.is_dir() {
-                    return Err(InvalidWorkingDirectory {
-                        path: cwd.to_string_lossy().to_string(),
-                    });
-                }
-                abs_path
-            }
-            None => self.workspace.root().to_path_buf(),
-        })
+        let root = self.workspace.root();
+        let Some(cwd) = self.task.working_directory() else {
+            return Ok(root.to_path_buf());
+        };
+        if self.task.is_custom() {
+            return Ok(root.join(cwd));
+        }
+
+        // Joining an absolute path replaces the root.
+        let abs_path = root.join(cwd);
+        let resolved = dunce::canonicalize(&abs_path)
+            .ok()
+            .filter(|path| path.is_dir())
+            .ok_or_else(|| InvalidWorkingDirectory::NotADirectory {
+                path: cwd.to_string_lossy().to_string(),
+            })?;
+        let root = dunce::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        if !resolved.starts_with(&root) {
+            return Err(InvalidWorkingDirectory::EscapesWorkspaceRoot {
+                path: resolved,
+                root,
+            });
+        }
+        Ok(abs_path)
     }
 
     /// Returns the full command that should be executed for this task. This
@@ -183,15 +407,19 @@ impl<'p> ExecutableTask<'p> {
     ///
     /// This function returns `None` if the task does not define a command to
     /// execute. This is the case for alias only commands.
-    pub(crate) fn full_command(&self) -> Option<String> {
-        let mut cmd = self.task.as_single_command()?.to_string();
+    pub(crate) fn full_command(&self) -> Result<Option<String>, FailedToParseShellScript> {
+        let Some(cmd) = self.task.as_single_command() else {
+            return Ok(None);
+        };
 
-        if !self.additional_args.is_empty() {
-            cmd.push(' ');
-            cmd.push_str(&self.additional_args.join(" "));
-        }
+        let command = if self.additional_args.is_empty() {
+            cmd.into_owned()
+        } else {
+            self.task_precedence
+                .combine(&cmd, &self.additional_args.join(" "))?
+        };
 
-        Some(cmd)
+        Ok(Some(self.prefixed(command)))
     }
 
     /// Returns an object that implements [`Display`] which outputs the command
@@ -206,7 +434,8 @@ impl<'p> ExecutableTask<'p> {
         command_env: &HashMap<String, String>,
         input: Option<&[u8]>,
     ) -> Result<RunOutput, TaskExecutionError> {
-        let Some(script) = self.as_deno_script()? else {
+        self.check_env_merge(command_env)?;
+        let Some(script) = self.as_deno_script(command_env)? else {
             return Ok(RunOutput {
                 exit_code: 0,
                 stdout: String::new(),
@@ -249,14 +478,14 @@ impl<'p> ExecutableTask<'p> {
     }
 
     /// Checks if the task can be skipped. If the task can be skipped, it
-    /// returns `CanSkip::Yes`. If the task cannot be skipped, it returns
+    /// returns `CanSkip::Yes` with the reason why. If the task cannot be skipped, it returns
     /// `CanSkip::No` and includes the hash of the task that caused the task
     /// to not be skipped - we can use this later to update the cache file
     /// quickly.
     pub(crate) async fn can_skip(&self, lock_file: &LockFile) -> Result<CanSkip, std::io::Error> {
         tracing::info!("Checking if task can be skipped");
         let cache_name = self.cache_name();
-        let cache_file = self.project().task_cache_folder().join(cache_name);
+        let cache_file = self.task_cache_folder().join(cache_name);
         if cache_file.exists() {
             let cache = tokio_fs::read_to_string(&cache_file).await?;
             let cache: TaskCache = serde_json::from_str(&cache)?;
@@ -265,13 +494,34 @@ impl<'p> ExecutableTask<'p> {
                 if hash.computation_hash() != cache.hash {
                     return Ok(CanSkip::No(Some(hash)));
                 } else {
-                    return Ok(CanSkip::Yes);
+                    return Ok(CanSkip::Yes(SkipReason {
+                        hash: cache.hash,
+                        inputs: hash.inputs.as_ref().map(|inputs| inputs.files.files.len()),
+                        outputs: hash
+                            .outputs
+                            .as_ref()
+                            .map(|outputs| outputs.files.files.len()),
+                    }));
                 }
             }
         }
         Ok(CanSkip::No(None))
     }
 
+    /// Returns true if the inputs of the task are unchanged since its cache was
+    /// saved, but its outputs are not. This indicates that the outputs were
+    /// modified outside of pixi.
+    pub(crate) async fn outputs_modified(&self, hash: &TaskHash) -> Result<bool, std::io::Error> {
+        let cache_file = self.task_cache_folder().join(self.cache_name());
+        if !cache_file.exists() {
+            return Ok(false);
+        }
+        let cache = tokio_fs::read_to_string(&cache_file).await?;
+        let cache: TaskCache = serde_json::from_str(&cache)?;
+        Ok(cache.inputs_hash == Some(hash.inputs_computation_hash())
+            && cache.hash != hash.computation_hash())
+    }
+
     /// Saves the cache of the task. This function will update the cache file
     /// with the new hash of the task (inputs and outputs). If the task has
     /// no hash, it will not save the cache.
@@ -280,7 +530,7 @@ impl<'p> ExecutableTask<'p> {
         lock_file: &LockFileDerivedData<'_>,
         previous_hash: Option<TaskHash>,
     ) -> Result<(), CacheUpdateError> {
-        let task_cache_folder = self.project().task_cache_folder();
+        let task_cache_folder = self.task_cache_folder();
         let cache_file = task_cache_folder.join(self.cache_name());
         let new_hash = if let Some(mut previous_hash) = previous_hash {
             previous_hash.update_output(self).await?;
@@ -293,9 +543,7 @@ impl<'p> ExecutableTask<'p> {
 
         tokio::fs::create_dir_all(&task_cache_folder).await?;
 
-        let cache = TaskCache {
-            hash: new_hash.computation_hash(),
-        };
+        let cache = TaskCache::from_hash(&new_hash);
         let cache = serde_json::to_string(&cache)?;
         Ok(tokio::fs::write(&cache_file, cache).await?)
     }
@@ -309,6 +557,19 @@ struct ExecutableTaskConsoleDisplay<'p, 't> {
 
 impl Display for ExecutableTaskConsoleDisplay<'_, '_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // The arguments are only shown separately when they are appended to
+        // the unprefixed command, or when they can't be combined with it.
+        if self.task.task_precedence != TaskPrecedence::Cli || self.task.command_prefix.is_some() {
+            if let Ok(command) = self.task.full_command() {
+                return write!(
+                    f,
+                    "{}",
+                    consts::TASK_STYLE
+                        .apply_to(command.as_deref().unwrap_or("<alias>"))
+                        .bold()
+                );
+            }
+        }
         let command = self.task.task.as_single_command();
         write!(
             f,
@@ -334,14 +595,43 @@ fn get_output_writer_and_handle() -> (ShellPipeWriter, JoinHandle<String>) {
     (writer, handle)
 }
 
+/// Returns true if the value of a task variable extends the existing value
+/// instead of replacing it, e.g. `PATH = "$PATH:/bin"`.
+fn extends_existing_value(key: &str, value: &str) -> bool {
+    value.contains(format!("${}", key).as_str())
+}
+
+/// The variables in the `env` of the task that are not set in the current
+/// shell but are set by the activation in `command_env`.
+fn activation_conflicts<'a>(
+    task: &'a Task,
+    command_env: &'a HashMap<String, String>,
+) -> impl Iterator<Item = &'a String> + 'a {
+    task.env()
+        .into_iter()
+        .flatten()
+        .filter(|(key, value)| {
+            !extends_existing_value(key, value)
+                && std::env::var(key.as_str()).is_err()
+                && command_env.contains_key(key.as_str())
+        })
+        .map(|(key, _)| key)
+}
+
 /// Task specific environment variables.
-fn get_export_specific_task_env(task: &Task) -> String {
+fn get_export_specific_task_env(task: &Task, command_env: &HashMap<String, String>) -> String {
+    let kept_activation_vars = match task.env_merge_strategy() {
+        EnvMergeStrategy::ActivationWins => activation_conflicts(task, command_env).collect_vec(),
+        EnvMergeStrategy::TaskWins | EnvMergeStrategy::Error => Vec::new(),
+    };
+
     // Append the environment variables if they don't exist
     let mut export = String::new();
     if let Some(env) = task.env() {
         for (key, value) in env {
-            if value.contains(format!("${}", key).as_str()) || std::env::var(key.as_str()).is_err()
-            {
+            if kept_activation_vars.contains(&key) {
+                tracing::info!("Environment variable {} already set by the activation", key);
+            } else if extends_existing_value(key, value) || std::env::var(key.as_str()).is_err() {
                 tracing::info!("Setting environment variable: {}=\"{}\"", key, value);
                 export.push_str(&format!("export \"{}={}\";\n", key, value));
             } else {
@@ -383,22 +673,56 @@ pub async fn get_task_env(
     .clone();
 
     // Add the current working directory to the environment
+    insert_init_cwd(&mut activation_env);
+
+    // Concatenate with the system environment variables
+    Ok(activation_env)
+}
+
+/// Determine the environment variables to use when executing a command in the
+/// prefix of an environment without activating it. Instead of running the
+/// activation scripts only the executable directories of the prefix are put on
+/// the `PATH`.
+pub fn get_prefix_only_task_env(environment: &Environment<'_>) -> HashMap<String, String> {
+    let mut env = get_prefix_only_environment_variables(environment);
+    insert_init_cwd(&mut env);
+    env
+}
+
+/// Determine the environment variables to use when executing a command in the
+/// prefix of an environment without running its activation scripts, see `pixi
+/// run --no-activation`. The executable directories of the prefix are put in
+/// front of the `PATH` of the current shell, whose variables are kept unless a
+/// clean environment is requested.
+pub fn get_unactivated_task_env(
+    environment: &Environment<'_>,
+    clean_env: bool,
+) -> HashMap<String, String> {
+    if clean_env {
+        return get_prefix_only_task_env(environment);
+    }
+    let mut env = get_inherited_prefix_environment_variables(environment);
+    insert_init_cwd(&mut env);
+    env
+}
+
+/// Adds the current working directory as `INIT_CWD` to the environment.
+fn insert_init_cwd(env: &mut HashMap<String, String>) {
     if let Ok(init_cwd) = std::env::current_dir() {
-        activation_env.insert(
+        env.insert(
             "INIT_CWD".to_string(),
             init_cwd.to_string_lossy().to_string(),
         );
     } else {
         tracing::warn!("Failed to get the current working directory for INIT_CWD.");
     }
-
-    // Concatenate with the system environment variables
-    Ok(activation_env)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use assert_matches::assert_matches;
+    use pixi_manifest::task::{CmdArgs, Custom};
     use std::path::Path;
 
     const PROJECT_BOILERPLATE: &str = r#"
@@ -410,6 +734,26 @@ mod tests {
         platforms = ["linux-64", "osx-64", "win-64", "osx-arm64", "linux-ppc64le", "linux-aarch64"]
         "#;
 
+    /// Returns the task with the given name in the default environment of the
+    /// workspace, without additional arguments.
+    fn executable_task<'p>(workspace: &'p Workspace, name: &str) -> ExecutableTask<'p> {
+        ExecutableTask {
+            workspace,
+            name: Some(name.into()),
+            task: Cow::Borrowed(
+                workspace
+                    .default_environment()
+                    .task(&TaskName::from(name), None)
+                    .unwrap(),
+            ),
+            run_environment: workspace.default_environment(),
+            additional_args: vec![],
+            task_cache_dir: None,
+            task_precedence: TaskPrecedence::default(),
+            command_prefix: None,
+        }
+    }
+
     #[test]
     fn test_export_specific_task_env() {
         let file_contents = r#"
@@ -427,11 +771,46 @@ mod tests {
             .task(&TaskName::from("test"), None)
             .unwrap();
 
-        let export = get_export_specific_task_env(task);
+        let export = get_export_specific_task_env(task, &HashMap::new());
 
         assert_eq!(export, "export \"FOO=bar\";\nexport \"BAR=$FOO\";\n");
     }
 
+    #[test]
+    fn test_env_merge_strategy() {
+        let file_contents = r#"
+            [tasks]
+            task-wins = {cmd = "test", env = {PIXI_TEST_MERGE = "task"}}
+            activation-wins = {cmd = "test", env = {PIXI_TEST_MERGE = "task"}, env-merge-strategy = "activation-wins"}
+            error = {cmd = "test", env = {PIXI_TEST_MERGE = "task"}, env-merge-strategy = "error"}
+            "#;
+        let workspace = Workspace::from_str(
+            Path::new("pixi.toml"),
+            &format!("{PROJECT_BOILERPLATE}\n{file_contents}"),
+        )
+        .unwrap();
+        let command_env =
+            HashMap::from([("PIXI_TEST_MERGE".to_string(), "activation".to_string())]);
+        let task = executable_task(&workspace, "task-wins");
+        assert_eq!(
+            task.as_script(&command_env).unwrap().unwrap(),
+            "export \"PIXI_TEST_MERGE=task\";\n\ntest "
+        );
+        task.check_env_merge(&command_env).unwrap();
+
+        let task = executable_task(&workspace, "activation-wins");
+        assert_eq!(task.as_script(&command_env).unwrap().unwrap(), "test ");
+        assert_eq!(
+            task.as_script(&HashMap::new()).unwrap().unwrap(),
+            "export \"PIXI_TEST_MERGE=task\";\n\ntest "
+        );
+
+        let task = executable_task(&workspace, "error");
+        let err = task.check_env_merge(&command_env).unwrap_err();
+        assert_eq!(err.variables, vec!["PIXI_TEST_MERGE"]);
+        task.check_env_merge(&HashMap::new()).unwrap();
+    }
+
     #[test]
     fn test_as_script() {
         let file_contents = r#"
@@ -456,9 +835,12 @@ mod tests {
             task: Cow::Borrowed(task),
             run_environment: workspace.default_environment(),
             additional_args: vec![],
+            task_cache_dir: None,
+            task_precedence: TaskPrecedence::default(),
+            command_prefix: None,
         };
 
-        let script = executable_task.as_script().unwrap();
+        let script = executable_task.as_script(&HashMap::new()).unwrap().unwrap();
         assert_eq!(script, "export \"FOO=bar\";\n\ntest ");
     }
 
@@ -486,4 +868,340 @@ mod tests {
                 .to_string()
         );
     }
+
+    #[test]
+    fn test_unactivated_task_env() {
+        let workspace = Workspace::from_str(
+            Path::new("pixi.toml"),
+            &format!("{PROJECT_BOILERPLATE}\n[activation.env]\nPIXI_TEST_ACTIVATION_ENV = \"set\""),
+        )
+        .unwrap();
+        let environment = workspace.default_environment();
+        std::env::set_var("PIXI_TEST_UNACTIVATED_INHERITED", "inherited");
+
+        // The variables of the shell are kept and the prefix is put in front
+        // of the PATH
+        let env = get_unactivated_task_env(&environment, false);
+        assert_eq!(
+            env.get("PIXI_TEST_UNACTIVATED_INHERITED")
+                .map(String::as_str),
+            Some("inherited")
+        );
+        assert_eq!(
+            env.get("PIXI_TEST_ACTIVATION_ENV").map(String::as_str),
+            Some("set")
+        );
+        let path = std::env::split_paths(&env["PATH"]).collect_vec();
+        assert_eq!(
+            path[0],
+            crate::activation::prefix_path_entries(&environment.dir())[0]
+        );
+        assert!(env.contains_key("INIT_CWD"));
+
+        // A clean environment only keeps the minimal set of variables
+        let env = get_unactivated_task_env(&environment, true);
+        assert!(!env.contains_key("PIXI_TEST_UNACTIVATED_INHERITED"));
+        assert_eq!(
+            env.get("PIXI_TEST_ACTIVATION_ENV").map(String::as_str),
+            Some("set")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_outputs_modified() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file_contents = r#"
+            [tasks]
+            build = {cmd = "build", inputs = ["input.txt"], outputs = ["output.txt"]}
+            "#;
+        let workspace = Workspace::from_str(
+            &tmp.path().join("pixi.toml"),
+            &format!("{PROJECT_BOILERPLATE}\n{file_contents}"),
+        )
+        .unwrap();
+        fs_err::write(tmp.path().join("input.txt"), "input").unwrap();
+        fs_err::write(tmp.path().join("output.txt"), "output").unwrap();
+
+        let task = executable_task(&workspace, "build");
+        let lock_file = LockFile::default();
+
+        // Store the cache of the task as if it ran successfully
+        let hash = TaskHash::from_task(&task, &lock_file)
+            .await
+            .unwrap()
+            .unwrap();
+        let cache_folder = workspace.task_cache_folder();
+        fs_err::create_dir_all(&cache_folder).unwrap();
+        let cache = serde_json::to_string(&TaskCache::from_hash(&hash)).unwrap();
+        fs_err::write(cache_folder.join(task.cache_name()), cache).unwrap();
+        assert!(!task.outputs_modified(&hash).await.unwrap());
+
+        // Modifying the outputs is detected
+        fs_err::write(tmp.path().join("output.txt"), "modified").unwrap();
+        let hash = TaskHash::from_task(&task, &lock_file)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(task.outputs_modified(&hash).await.unwrap());
+
+        // Unless the inputs changed as well
+        fs_err::write(tmp.path().join("input.txt"), "modified").unwrap();
+        let hash = TaskHash::from_task(&task, &lock_file)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!task.outputs_modified(&hash).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_can_skip_reason() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file_contents = r#"
+            [tasks]
+            build = {cmd = "build", inputs = ["input.txt"], outputs = ["out-*.txt"]}
+            "#;
+        let workspace = Workspace::from_str(
+            &tmp.path().join("pixi.toml"),
+            &format!("{PROJECT_BOILERPLATE}\n{file_contents}"),
+        )
+        .unwrap();
+        fs_err::write(tmp.path().join("input.txt"), "input").unwrap();
+        fs_err::write(tmp.path().join("out-1.txt"), "output").unwrap();
+        fs_err::write(tmp.path().join("out-2.txt"), "output").unwrap();
+
+        let task = executable_task(&workspace, "build");
+        let lock_file = LockFile::default();
+
+        // Without a cache the task can't be skipped
+        assert!(matches!(
+            task.can_skip(&lock_file).await.unwrap(),
+            CanSkip::No(None)
+        ));
+
+        // Store the cache of the task as if it ran successfully
+        let hash = TaskHash::from_task(&task, &lock_file)
+            .await
+            .unwrap()
+            .unwrap();
+        let cache_folder = workspace.task_cache_folder();
+        fs_err::create_dir_all(&cache_folder).unwrap();
+        let cache = serde_json::to_string(&TaskCache::from_hash(&hash)).unwrap();
+        fs_err::write(cache_folder.join(task.cache_name()), cache).unwrap();
+
+        let CanSkip::Yes(reason) = task.can_skip(&lock_file).await.unwrap() else {
+            panic!("expected a cache hit");
+        };
+        assert_eq!(
+            reason,
+            SkipReason {
+                hash: hash.computation_hash(),
+                inputs: Some(1),
+                outputs: Some(2),
+            }
+        );
+        assert_eq!(
+            reason.to_string(),
+            format!(
+                "1 input file(s) unchanged, 2 output file(s) present (hash {})",
+                hash.computation_hash()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_task_cache_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file_contents = r#"
+            [tasks]
+            build = {cmd = "build", inputs = ["input.txt"]}
+            "#;
+        let workspace = Workspace::from_str(
+            &tmp.path().join("pixi.toml"),
+            &format!("{PROJECT_BOILERPLATE}\n{file_contents}"),
+        )
+        .unwrap();
+        fs_err::write(tmp.path().join("input.txt"), "input").unwrap();
+
+        let task = |task_cache_dir| ExecutableTask {
+            task_cache_dir,
+            ..executable_task(&workspace, "build")
+        };
+        let cache_dir = tmp.path().join("custom-cache");
+        let custom = task(Some(cache_dir.clone()));
+        assert_eq!(custom.task_cache_folder(), cache_dir);
+
+        // Store the cache in the custom directory as if the task ran
+        let lock_file = LockFile::default();
+        let hash = TaskHash::from_task(&custom, &lock_file)
+            .await
+            .unwrap()
+            .unwrap();
+        fs_err::create_dir_all(&cache_dir).unwrap();
+        let cache = serde_json::to_string(&TaskCache::from_hash(&hash)).unwrap();
+        fs_err::write(cache_dir.join(custom.cache_name()), cache).unwrap();
+
+        // The cache is only found in the custom directory, the default one is
+        // left untouched
+        assert!(matches!(
+            custom.can_skip(&lock_file).await.unwrap(),
+            CanSkip::Yes(_)
+        ));
+        assert!(matches!(
+            task(None).can_skip(&lock_file).await.unwrap(),
+            CanSkip::No(None)
+        ));
+        assert!(!workspace.task_cache_folder().exists());
+    }
+
+    #[test]
+    fn test_task_precedence() {
+        let file_contents = r#"
+            [tasks]
+            build = "cargo build --release"
+            "#;
+        let workspace = Workspace::from_str(
+            Path::new("pixi.toml"),
+            &format!("{PROJECT_BOILERPLATE}\n{file_contents}"),
+        )
+        .unwrap();
+        let task = ExecutableTask {
+            additional_args: vec!["--offline".to_string()],
+            ..executable_task(&workspace, "build")
+        };
+        let command = |task_precedence| {
+            task.clone()
+                .with_task_precedence(task_precedence)
+                .full_command()
+                .unwrap()
+                .unwrap()
+        };
+
+        assert_eq!(
+            command(TaskPrecedence::Cli),
+            "cargo build --release --offline"
+        );
+        assert_eq!(
+            command(TaskPrecedence::Manifest),
+            "cargo --offline build --release"
+        );
+        assert_eq!(command(TaskPrecedence::CliReplaces), "--offline");
+
+        // Without additional arguments the command is unchanged
+        let task = ExecutableTask {
+            additional_args: vec![],
+            ..task.clone()
+        }
+        .with_task_precedence(TaskPrecedence::CliReplaces);
+        assert_eq!(
+            task.full_command().unwrap().unwrap(),
+            "cargo build --release"
+        );
+
+        // The command prefix comes before the combined command
+        let task = task.with_command_prefix("gdb --args");
+        assert_eq!(
+            task.full_command().unwrap().unwrap(),
+            "gdb --args cargo build --release"
+        );
+    }
+
+    #[test]
+    fn test_task_precedence_manifest() {
+        let combine = |command: &str| TaskPrecedence::Manifest.combine(command, "'--offline'");
+
+        assert_eq!(combine("cargo").unwrap(), "cargo '--offline'");
+        assert_eq!(
+            combine("  cargo build").unwrap(),
+            "  cargo '--offline' build"
+        );
+        assert_eq!(
+            combine("FOO=1 BAR=\"a b\" pytest -x").unwrap(),
+            "FOO=1 BAR=\"a b\" pytest '--offline' -x"
+        );
+        assert_eq!(
+            combine("\"my tool\" x").unwrap(),
+            "\"my tool\" '--offline' x"
+        );
+        assert_eq!(
+            combine("'my tool'>out.txt").unwrap(),
+            "'my tool' '--offline'>out.txt"
+        );
+        assert_eq!(
+            combine("cargo build && cargo test --release").unwrap(),
+            "cargo build && cargo '--offline' test --release"
+        );
+        assert_eq!(
+            combine("cd src; make all || make clean").unwrap(),
+            "cd src; make all || make '--offline' clean"
+        );
+
+        // Commands without a program in the last sequential command are not
+        // supported.
+        assert!(combine("cargo build | tee log.txt").is_err());
+        assert!(combine("(cd src && make)").is_err());
+        assert!(combine("! cargo test").is_err());
+        assert!(combine("cargo build &").is_err());
+        assert!(combine("FOO=1").is_err());
+        assert!(combine("> out.txt cargo build").is_err());
+        assert!(combine("\"unterminated").is_err());
+    }
+
+    #[test]
+    fn test_working_directory_escapes_workspace_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("workspace");
+        fs_err::create_dir_all(root.join("sub")).unwrap();
+        fs_err::create_dir_all(tmp.path().join("outside")).unwrap();
+        let file_contents = format!(
+            r#"
+            [tasks]
+            sub = {{cmd = "test", cwd = "sub"}}
+            parent = {{cmd = "test", cwd = "sub/../.."}}
+            sibling = {{cmd = "test", cwd = "../outside"}}
+            absolute = {{cmd = "test", cwd = "{}"}}
+            missing = {{cmd = "test", cwd = "missing"}}
+            "#,
+            tmp.path()
+                .join("outside")
+                .display()
+                .to_string()
+                .replace('\\', "/")
+        );
+        let workspace = Workspace::from_str(
+            &root.join("pixi.toml"),
+            &format!("{PROJECT_BOILERPLATE}\n{file_contents}"),
+        )
+        .unwrap();
+        let working_directory = |name: &str| executable_task(&workspace, name).working_directory();
+
+        assert_eq!(working_directory("sub").unwrap(), root.join("sub"));
+        for name in ["parent", "sibling", "absolute"] {
+            assert_matches!(
+                working_directory(name),
+                Err(InvalidWorkingDirectory::EscapesWorkspaceRoot { .. })
+            );
+        }
+        assert_matches!(
+            working_directory("missing"),
+            Err(InvalidWorkingDirectory::NotADirectory { .. })
+        );
+
+        // Custom commands run in the directory pixi was started from, even if
+        // that is outside of the workspace.
+        let custom = ExecutableTask {
+            name: None,
+            task: Cow::Owned(
+                Custom {
+                    cmd: CmdArgs::Single("test".to_string()),
+                    cwd: Some(tmp.path().join("outside")),
+                }
+                .into(),
+            ),
+            ..executable_task(&workspace, "sub")
+        };
+        assert_eq!(
+            custom.working_directory().unwrap(),
+            tmp.path().join("outside")
+        );
+    }
 }
@@ -10,8 +10,9 @@ pub use pixi_manifest::{Task, TaskName};
 pub use task_hash::{ComputationHash, EnvironmentHash, InputHashes, TaskHash};
 
 pub use executable_task::{
-    get_task_env, CanSkip, ExecutableTask, FailedToParseShellScript, InvalidWorkingDirectory,
-    RunOutput, TaskExecutionError,
+    get_prefix_only_task_env, get_task_env, get_unactivated_task_env, CanSkip, EnvMergeConflict,
+    ExecutableTask, FailedToParseShellScript, InvalidWorkingDirectory, RunOutput, SkipReason,
+    TaskExecutionError, TaskPrecedence,
 };
 pub use task_environment::{
     AmbiguousTask, FindTaskError, FindTaskSource, SearchEnvironments, TaskAndEnvironment,
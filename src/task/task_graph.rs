@@ -9,14 +9,14 @@ use std::{
 use itertools::Itertools;
 use miette::Diagnostic;
 use pixi_manifest::{
-    task::{CmdArgs, Custom},
+    task::{Alias, CmdArgs, Custom},
     Task, TaskName,
 };
 use thiserror::Error;
 
 use crate::{
     task::{
-        error::{AmbiguousTaskError, MissingTaskError},
+        error::{AmbiguousTaskError, InvalidGlobError, InvalidGlobsError, MissingTaskError},
         task_environment::{FindTaskError, FindTaskSource, SearchEnvironments},
         TaskDisambiguation,
     },
@@ -128,6 +128,12 @@ impl<'p> TaskGraph<'p> {
         self.project
     }
 
+    /// Returns the task that was requested on the command line, all other
+    /// tasks in the graph are its (transitive) dependencies.
+    pub fn root(&self) -> TaskId {
+        TaskId(0)
+    }
+
     /// Constructs a new [`TaskGraph`] from a list of command line arguments.
     pub fn from_cmd_args<D: TaskDisambiguation<'p>>(
         project: &'p Workspace,
@@ -161,29 +167,43 @@ impl<'p> TaskGraph<'p> {
                         Some(explicit_env) if task_env.is_default() => explicit_env,
                         _ => task_env,
                     };
+                    let name = TaskName::from(args.remove(0));
+
+                    // A task whose condition doesn't hold is not executed, and
+                    // neither are its dependencies.
+                    let task = if condition_holds(&name, task)? {
+                        Cow::Borrowed(task)
+                    } else {
+                        Cow::Owned(Task::Alias(Alias {
+                            depends_on: Vec::new(),
+                            description: task.description().map(ToString::to_string),
+                        }))
+                    };
                     if skip_deps {
-                        return Ok(Self {
+                        return Self {
                             project,
                             nodes: vec![TaskNode {
-                                name: Some(args.remove(0).into()),
-                                task: Cow::Borrowed(task),
+                                name: Some(name),
+                                task,
                                 run_environment: run_env,
                                 additional_args: args,
                                 dependencies: vec![],
                             }],
-                        });
+                        }
+                        .validate_globs();
                     }
                     return Self::from_root(
                         project,
                         search_envs,
                         TaskNode {
-                            name: Some(args.remove(0).into()),
-                            task: Cow::Borrowed(task),
+                            name: Some(name),
+                            task,
                             run_environment: run_env,
                             additional_args: args,
                             dependencies: vec![],
                         },
-                    );
+                    )?
+                    .validate_globs();
                 }
             }
         }
@@ -225,6 +245,30 @@ impl<'p> TaskGraph<'p> {
         )
     }
 
+    /// Constructs a new [`TaskGraph`] that runs a custom command, without
+    /// looking up its first word as a task of the workspace.
+    pub fn from_custom<D: TaskDisambiguation<'p>>(
+        project: &'p Workspace,
+        search_envs: &SearchEnvironments<'p, D>,
+        custom: Custom,
+        additional_args: Vec<String>,
+    ) -> Self {
+        let run_environment = search_envs
+            .explicit_environment
+            .clone()
+            .unwrap_or_else(|| project.default_environment());
+        Self {
+            project,
+            nodes: vec![TaskNode {
+                name: None,
+                task: Cow::Owned(custom.into()),
+                run_environment,
+                additional_args,
+                dependencies: vec![],
+            }],
+        }
+    }
+
     /// Constructs a new instance of a [`TaskGraph`] from a root task.
     fn from_root<D: TaskDisambiguation<'p>>(
         project: &'p Workspace,
@@ -275,6 +319,11 @@ impl<'p> TaskGraph<'p> {
                     Ok(result) => result,
                 };
 
+                // Dependencies whose condition doesn't hold are dropped from the graph.
+                if !condition_holds(&dependency, task_dependency)? {
+                    continue;
+                }
+
                 // Add the node to the graph
                 let task_id = TaskId(nodes.len());
                 nodes.push(TaskNode {
@@ -299,6 +348,43 @@ impl<'p> TaskGraph<'p> {
         Ok(Self { project, nodes })
     }
 
+    /// Checks that the `inputs` and `outputs` of all tasks in the graph are
+    /// valid glob patterns, so that invalid patterns are reported before any
+    /// task is executed.
+    fn validate_globs(self) -> Result<Self, TaskGraphError> {
+        let mut errors = Vec::new();
+        for node in &self.nodes {
+            let Some(task_name) = &node.name else {
+                continue;
+            };
+            let globs = [
+                ("inputs", node.task.inputs()),
+                ("outputs", node.task.outputs()),
+            ];
+            for (kind, glob) in globs
+                .into_iter()
+                .flat_map(|(kind, globs)| globs.unwrap_or_default().iter().map(move |g| (kind, g)))
+            {
+                // Exclusion globs are prefixed with `!`, see `pixi_glob::GlobSet`.
+                let pattern = glob.strip_prefix('!').unwrap_or(glob);
+                if let Err(source) = wax::Glob::new(pattern) {
+                    errors.push(InvalidGlobError {
+                        task_name: task_name.clone(),
+                        kind,
+                        glob: glob.clone(),
+                        source,
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(TaskGraphError::InvalidGlobs(InvalidGlobsError { errors }))
+        }
+    }
+
     /// Returns the topological order of the tasks in the graph.
     ///
     /// The topological order is the order in which the tasks should be executed
@@ -331,6 +417,41 @@ impl<'p> TaskGraph<'p> {
             order.push(id);
         }
     }
+
+    /// Returns the tasks that form a cycle in the graph, if there is one. The
+    /// first task of the cycle is repeated at the end.
+    pub fn find_cycle(&self) -> Option<Vec<TaskId>> {
+        let mut visited = HashSet::new();
+        let mut stack = Vec::new();
+
+        return (0..self.nodes.len())
+            .find_map(|i| visit(TaskId(i), &self.nodes, &mut visited, &mut stack));
+
+        fn visit(
+            id: TaskId,
+            nodes: &[TaskNode<'_>],
+            visited: &mut HashSet<TaskId>,
+            stack: &mut Vec<TaskId>,
+        ) -> Option<Vec<TaskId>> {
+            if let Some(start) = stack.iter().position(|other| *other == id) {
+                let mut cycle = stack[start..].to_vec();
+                cycle.push(id);
+                return Some(cycle);
+            }
+            if !visited.insert(id) {
+                return None;
+            }
+
+            stack.push(id);
+            for dependency in nodes[id.0].dependencies.iter() {
+                if let Some(cycle) = visit(*dependency, nodes, visited, stack) {
+                    return Some(cycle);
+                }
+            }
+            stack.pop();
+            None
+        }
+    }
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -344,6 +465,44 @@ pub enum TaskGraphError {
 
     #[error("could not split task, assuming non valid task")]
     InvalidTask,
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    InvalidGlobs(InvalidGlobsError),
+
+    #[error("failed to evaluate the condition '{condition}' of task '{task}'")]
+    InvalidCondition {
+        task: TaskName,
+        condition: String,
+        #[source]
+        source: minijinja::Error,
+    },
+}
+
+/// Returns whether the `condition` of the task holds, tasks without a condition
+/// always run. The condition is rendered with the environment variables of the
+/// shell that pixi runs in as `env`, e.g. `{{ env.CI == 'true' }}`, and holds
+/// if it renders to `true`.
+fn condition_holds(name: &TaskName, task: &Task) -> Result<bool, TaskGraphError> {
+    let Some(condition) = task.condition() else {
+        return Ok(true);
+    };
+    let vars: HashMap<String, String> = env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+        .collect();
+    let holds = minijinja::Environment::new()
+        .render_str(condition, minijinja::context! { env => vars })
+        .map_err(|source| TaskGraphError::InvalidCondition {
+            task: name.clone(),
+            condition: condition.to_string(),
+            source,
+        })?
+        .trim()
+        == "true";
+    if !holds {
+        tracing::info!("skipping task '{name}', its condition '{condition}' doesn't hold");
+    }
+    Ok(holds)
 }
 
 #[cfg(test)]
@@ -354,7 +513,10 @@ mod test {
     use rattler_conda_types::Platform;
 
     use crate::{
-        task::{task_environment::SearchEnvironments, task_graph::TaskGraph},
+        task::{
+            task_environment::SearchEnvironments,
+            task_graph::{TaskGraph, TaskGraphError},
+        },
         Workspace,
     };
 
@@ -644,4 +806,128 @@ mod test {
             vec!["echo foo", "echo bar"]
         );
     }
+
+    #[test]
+    fn test_conditional_tasks() {
+        let project = r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64", "linux-riscv64"]
+
+        [tasks]
+        always = { cmd = "echo always", condition = "{{ env.PATH is defined }}" }
+        never = { cmd = "echo never", condition = "{{ env.PIXI_TEST_CONDITION_NOT_SET == 'true' }}", depends-on = ["always"] }
+        build = { cmd = "echo build", depends-on = ["always", "never"] }
+        broken = { cmd = "echo broken", condition = "{{ env.CI == }}" }
+    "#;
+        // The dependency on the skipped task is dropped
+        assert_eq!(
+            commands_in_order(project, &["build"], None, None, false),
+            vec!["echo always", "echo build"]
+        );
+        // A skipped task doesn't run its dependencies either
+        assert!(commands_in_order(project, &["never"], None, None, false).is_empty());
+
+        let workspace = Workspace::from_str(Path::new("pixi.toml"), project).unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&workspace, None, None);
+        let graph =
+            TaskGraph::from_cmd_args(&workspace, &search_envs, vec!["broken".to_string()], false);
+        assert!(matches!(
+            graph,
+            Err(TaskGraphError::InvalidCondition { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_globs() {
+        let project = Workspace::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64", "linux-riscv64"]
+
+        [tasks]
+        foo = { cmd = "echo foo", inputs = ["src/**/*.rs", "!src/[a-"] }
+        bar = { cmd = "echo bar", depends-on = ["foo"], outputs = ["build/{a,b"] }
+    "#,
+        )
+        .unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None);
+
+        let err = TaskGraph::from_cmd_args(&project, &search_envs, vec!["bar".to_string()], false)
+            .unwrap_err();
+        let TaskGraphError::InvalidGlobs(err) = err else {
+            panic!("expected invalid globs error, got {err:?}");
+        };
+        let globs = err
+            .errors
+            .iter()
+            .map(|e| e.glob.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(globs, vec!["build/{a,b", "!src/[a-"]);
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        let project = Workspace::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        root = {cmd="echo root", depends-on=["task1"]}
+        task1 = {cmd="echo task1", depends-on=["root"]}
+        top = {cmd="echo top", depends-on=["task1"]}
+        other = {cmd="echo other", depends-on=["top"]}
+    "#,
+        )
+        .unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None);
+        let names = |task: &str| {
+            let graph =
+                TaskGraph::from_cmd_args(&project, &search_envs, vec![task.to_string()], false)
+                    .unwrap();
+            graph.find_cycle().map(|cycle| {
+                cycle
+                    .into_iter()
+                    .map(|id| graph[id].name.as_ref().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(
+            names("other"),
+            Some(vec![
+                "task1".to_string(),
+                "root".to_string(),
+                "task1".to_string()
+            ])
+        );
+
+        let project = Workspace::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        root = "echo root"
+        task1 = {cmd="echo task1", depends-on=["root"]}
+        task2 = {cmd="echo task2", depends-on=["root"]}
+        top = {cmd="echo top", depends-on=["task1","task2"]}
+    "#,
+        )
+        .unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None);
+        let graph =
+            TaskGraph::from_cmd_args(&project, &search_envs, vec!["top".to_string()], false)
+                .unwrap();
+        assert_eq!(graph.find_cycle(), None);
+    }
 }
@@ -1,4 +1,6 @@
-use crate::task::{ExecutableTask, FileHashes, FileHashesError, InvalidWorkingDirectory};
+use crate::task::{
+    ExecutableTask, FailedToParseShellScript, FileHashes, FileHashesError, InvalidWorkingDirectory,
+};
 use crate::workspace;
 use miette::Diagnostic;
 use rattler_lock::LockFile;
@@ -32,6 +34,21 @@ impl Display for ComputationHash {
 pub struct TaskCache {
     /// The hash of the task.
     pub hash: ComputationHash,
+
+    /// The hash of the task without its outputs. Used to detect outputs that
+    /// were modified outside of pixi.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inputs_hash: Option<ComputationHash>,
+}
+
+impl TaskCache {
+    /// Constructs the cache of a task from its hashes.
+    pub fn from_hash(hash: &TaskHash) -> Self {
+        Self {
+            hash: hash.computation_hash(),
+            inputs_hash: Some(hash.inputs_computation_hash()),
+        }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -118,7 +135,7 @@ impl TaskHash {
         }
 
         Ok(Some(Self {
-            command: task.full_command(),
+            command: task.full_command()?,
             outputs: output_hashes,
             inputs: input_hashes,
             // Skipping environment variables used for caching the task
@@ -147,6 +164,15 @@ impl TaskHash {
         self.environment.hash(&mut hasher);
         ComputationHash(format!("{:x}", hasher.finish()))
     }
+
+    /// Computes a single hash for the task, ignoring its outputs.
+    pub fn inputs_computation_hash(&self) -> ComputationHash {
+        let mut hasher = Xxh3::new();
+        self.command.hash(&mut hasher);
+        self.inputs.hash(&mut hasher);
+        self.environment.hash(&mut hasher);
+        ComputationHash(format!("{:x}", hasher.finish()))
+    }
 }
 
 /// The combination of all the hashes of the inputs of a task.
@@ -223,4 +249,7 @@ pub enum InputHashesError {
 
     #[error(transparent)]
     InvalidWorkingDirectory(#[from] InvalidWorkingDirectory),
+
+    #[error(transparent)]
+    FailedToParseShellScript(#[from] FailedToParseShellScript),
 }
@@ -312,6 +312,7 @@ impl<'p> Environment<'p> {
                     environments_platforms: self.platforms().into_iter().collect(),
                     environment: self.name().clone(),
                     platform,
+                    task: None,
                 });
             }
         }
@@ -21,12 +21,23 @@ pub struct UnsupportedPlatformError {
 
     /// The platform that was requested
     pub platform: Platform,
+
+    /// The task that was requested to run on the platform, if any.
+    pub task: Option<TaskName>,
 }
 
 impl Error for UnsupportedPlatformError {}
 
 impl Display for UnsupportedPlatformError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(task) = &self.task {
+            write!(
+                f,
+                "the task '{}' can not run on '{}': ",
+                task.fancy_display(),
+                self.platform
+            )?;
+        }
         match &self.environment {
             EnvironmentName::Default => {
                 write!(
@@ -51,9 +62,20 @@ impl Diagnostic for UnsupportedPlatformError {
     }
 
     fn help(&self) -> Option<Box<dyn Display + '_>> {
+        if self.environments_platforms.is_empty() {
+            return Some(Box::new(format!(
+                "the environment '{}' doesn't support any platform",
+                self.environment.fancy_display()
+            )));
+        }
         Some(Box::new(format!(
-            "supported platforms are {}",
-            self.environments_platforms.iter().format(", ")
+            "the environment '{}' supports {}",
+            self.environment.fancy_display(),
+            self.environments_platforms
+                .iter()
+                .map(Platform::as_str)
+                .sorted()
+                .format(", ")
         )))
     }
 
@@ -102,6 +102,7 @@ pub(crate) fn verify_current_platform_can_run_environment(
                 environments_platforms: environment.platforms().into_iter().collect_vec(),
                 platform: current_platform,
                 environment: environment.name().clone(),
+                task: None,
             },
         )));
     }
@@ -635,6 +635,7 @@ impl TasksControl<'_> {
         name: TaskName,
         platform: Option<Platform>,
         feature_name: Option<String>,
+        prune_dependents: bool,
     ) -> miette::Result<()> {
         task::execute(task::Args {
             workspace_config: WorkspaceConfig {
@@ -644,6 +645,7 @@ impl TasksControl<'_> {
                 names: vec![name],
                 platform,
                 feature: feature_name,
+                prune_dependents,
             }),
         })
         .await
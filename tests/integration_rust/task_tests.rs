@@ -29,7 +29,7 @@ pub async fn add_remove_task() {
 
     // Remove the task
     pixi.tasks()
-        .remove("test".into(), None, None)
+        .remove("test".into(), None, None, false)
         .await
         .unwrap();
     assert_eq!(
@@ -89,6 +89,58 @@ pub async fn add_command_types() {
     assert!(matches!(task, Task::Alias(a) if a.depends_on.first().unwrap().as_str() == "test"));
 }
 
+#[tokio::test]
+pub async fn remove_task_with_dependents() {
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().without_channels().await.unwrap();
+
+    pixi.tasks()
+        .add("build".into(), None, FeatureName::Default)
+        .with_commands(["echo build"])
+        .execute()
+        .await
+        .unwrap();
+    pixi.tasks()
+        .add("lint".into(), None, FeatureName::Default)
+        .with_commands(["echo lint"])
+        .execute()
+        .await
+        .unwrap();
+    pixi.tasks()
+        .add("test".into(), None, FeatureName::Default)
+        .with_commands(["echo test"])
+        .with_depends_on(vec!["build".into(), "lint".into()])
+        .execute()
+        .await
+        .unwrap();
+
+    let depends_on = |name: &str| {
+        let workspace = pixi.workspace().unwrap();
+        let tasks = workspace.default_environment().tasks(None).unwrap();
+        tasks
+            .get(&TaskName::from(name))
+            .unwrap()
+            .depends_on()
+            .iter()
+            .map(|dep| dep.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // Without pruning, the orphaned reference is kept
+    pixi.tasks()
+        .remove("build".into(), None, None, false)
+        .await
+        .unwrap();
+    assert_eq!(depends_on("test"), vec!["build", "lint"]);
+
+    // With pruning, the reference to the removed task is removed as well
+    pixi.tasks()
+        .remove("lint".into(), None, None, true)
+        .await
+        .unwrap();
+    assert_eq!(depends_on("test"), vec!["build"]);
+}
+
 #[tokio::test]
 async fn test_alias() {
     let pixi = PixiControl::new().unwrap();
@@ -164,7 +216,7 @@ pub async fn add_remove_target_specific_task() {
 
     // Remove the task
     pixi.tasks()
-        .remove("test".into(), Some(Platform::Win64), None)
+        .remove("test".into(), Some(Platform::Win64), None, false)
         .await
         .unwrap();
     assert_eq!(
@@ -273,23 +325,19 @@ async fn test_clean_env() {
         .await
         .unwrap();
 
-    let run = pixi.run(Args {
-        task: vec!["env-test".to_string()],
-        workspace_config: WorkspaceConfig {
-            manifest_path: None,
-        },
-        clean_env: true,
-        ..Default::default()
-    });
-
-    if cfg!(windows) {
-        // Clean env running not supported on windows.
-        run.await.unwrap_err();
-    } else {
-        let result = run.await.unwrap();
-        assert_eq!(result.exit_code, 0);
-        assert_eq!(result.stdout, "Hello is:\n");
-    }
+    let result = pixi
+        .run(Args {
+            task: vec!["env-test".to_string()],
+            workspace_config: WorkspaceConfig {
+                manifest_path: None,
+            },
+            clean_env: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.stdout, "Hello is:\n");
 
     let result = pixi
         .run(Args {